@@ -1,4 +1,6 @@
 mod app;
+#[cfg(feature = "web")]
+mod input_device_picker;
 mod slider;
 mod toggle;
 
@@ -1,11 +1,14 @@
+#[cfg(feature = "web")]
+use super::input_device_picker::InputDevicePicker;
 use super::{
     slider::ParameterSlider,
     toggle::{ParameterToggle, Toggle},
 };
-use audio_stream::AudioStream;
+use audio_stream::{AudioStream, ProcessorLoad, StreamConfig};
 use dioxus::prelude::*;
 use freeverb_module::{FreeverbModule, FreeverbParameters};
-use std::sync::Arc;
+use futures_timer::Delay;
+use std::{sync::Arc, time::Duration};
 
 const MAIN_CSS: Asset = asset!("/assets/main.css");
 
@@ -21,21 +24,14 @@ impl PartialEq for FreeverbStream {
 
 #[component]
 pub fn App() -> Element {
+    // Incremented on the web each time audio is initialized with a new input device.
+    let audio_generation = use_signal(|| 0usize);
+
+    // On the web, audio is initialized by the input device picker.
     #[cfg(feature = "web")]
-    {
-        // Initialize the audio stream's processor Wasm.
-        use_future(async || {
-            if let Err(error) = audio_stream::initialize_audio(
-                "./freeverb_bg.wasm",
-                "./freeverb.js",
-                "./audio_stream_worklet.js",
-            )
-            .await
-            {
-                error!("Failed to initialize audio: {error:?}");
-            }
-        });
-    }
+    let input_device_picker = rsx! { InputDevicePicker { audio_generation } };
+    #[cfg(not(feature = "web"))]
+    let input_device_picker = rsx! {};
 
     // Load the parameters when the app is first initialized.
     let parameters = use_hook(FreeverbParameters::default);
@@ -43,9 +39,12 @@ pub fn App() -> Element {
     // Create a signal for enabling or disabling the audio stream.
     let mut audio_enabled = use_signal(|| false);
     // Create or destroy the audio stream when `audio_enabled` changes.
+    // The stream is also recreated when audio has been initialized with a new input device.
     let audio_stream = use_memo(move || {
+        audio_generation();
+
         if audio_enabled() {
-            match AudioStream::new() {
+            match AudioStream::new(StreamConfig::default()) {
                 Ok(stream) => Some(FreeverbStream(Arc::new(stream))),
                 Err(error) => {
                     error!("Failed to create audio stream: {error}");
@@ -65,6 +64,22 @@ pub fn App() -> Element {
             .map(|stream| stream.0.to_processor())
     });
 
+    // Poll the processor's load while the audio stream is running
+    let mut processor_load = use_signal(ProcessorLoad::default);
+    use_future(move || async move {
+        loop {
+            let load = audio_stream
+                .peek()
+                .as_ref()
+                .map(|stream| stream.0.processor_load());
+            if let Some(load) = load {
+                processor_load.set(load);
+            }
+
+            Delay::new(Duration::from_millis(250)).await;
+        }
+    });
+
     rsx! {
         document::Link { rel: "stylesheet", href: MAIN_CSS }
 
@@ -84,11 +99,20 @@ pub fn App() -> Element {
             }
         }
 
+        {input_device_picker}
+
         ParameterSlider { parameter: parameters.dampening, to_processor: to_processor }
         ParameterSlider { parameter: parameters.width, to_processor: to_processor }
         ParameterSlider { parameter: parameters.room_size, to_processor: to_processor }
         ParameterToggle { parameter: parameters.freeze, to_processor: to_processor }
         ParameterSlider { parameter: parameters.dry, to_processor: to_processor }
         ParameterSlider { parameter: parameters.wet, to_processor: to_processor }
+
+        if audio_enabled() {
+            div {
+                class: "status",
+                "DSP load: {processor_load}"
+            }
+        }
     }
 }
@@ -0,0 +1,75 @@
+use audio_stream::{AudioOptions, InputDeviceInfo};
+use dioxus::prelude::*;
+
+/// Initializes audio in the browser, with a choice of the available input devices
+///
+/// Audio is initialized again whenever a different input device is chosen, and
+/// `audio_generation` is incremented each time audio is ready so that the app's stream can be
+/// recreated using the new device.
+#[component]
+pub fn InputDevicePicker(audio_generation: Signal<usize>) -> Element {
+    let mut audio_generation = audio_generation;
+
+    // `None` uses the browser's default input device
+    let mut input_device_id = use_signal(|| None::<String>);
+    let mut input_devices = use_signal(Vec::<InputDeviceInfo>::new);
+
+    // Initialize the audio stream's processor Wasm, rerunning when the input device changes.
+    use_effect(move || {
+        let options = AudioOptions {
+            input_device_id: input_device_id(),
+            ..Default::default()
+        };
+
+        spawn(async move {
+            if let Err(error) = audio_stream::initialize_audio(
+                "./freeverb_bg.wasm",
+                "./freeverb.js",
+                "./audio_stream_worklet.js",
+                options,
+            )
+            .await
+            {
+                error!("Failed to initialize audio: {error}");
+                return;
+            }
+            *audio_generation.write() += 1;
+
+            // Browsers only provide the devices' details once mic access has been granted
+            match audio_stream::available_input_devices().await {
+                Ok(devices) => input_devices.set(devices),
+                Err(error) => error!("Failed to list audio input devices: {error}"),
+            }
+        });
+    });
+
+    rsx! {
+        select {
+            class: "input-device",
+            onchange: move |event| {
+                let device_id = event.value();
+                input_device_id.set((!device_id.is_empty()).then_some(device_id));
+            },
+
+            option { value: "", "Default Input" }
+
+            for (index, device) in input_devices()
+                .into_iter()
+                .filter(|device| !device.device_id.is_empty())
+                .enumerate()
+            {
+                option {
+                    key: "{device.device_id}",
+                    value: "{device.device_id}",
+                    selected: input_device_id.read().as_ref() == Some(&device.device_id),
+
+                    if device.label.is_empty() {
+                        "Input {index + 1}"
+                    } else {
+                        "{device.label}"
+                    }
+                }
+            }
+        }
+    }
+}
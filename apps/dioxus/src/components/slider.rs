@@ -1,13 +1,17 @@
-use audio_module::{FloatParameter, Parameter, PushMessage, ToProcessor};
+use audio_module::{Parameter, PushMessage, ToProcessor};
 use audio_stream::ToProcessorSender;
 use dioxus::prelude::*;
 use dioxus_primitives::slider::{
     self, SliderRangeProps, SliderThumbProps, SliderTrackProps, SliderValue,
 };
 
+/// A slider for float, int, and enum parameters
+///
+/// Values of stepped parameters snap to the parameter's steps, with enums showing the selected
+/// variant's label.
 #[component]
-pub fn ParameterSlider(
-    parameter: FloatParameter,
+pub fn ParameterSlider<P: Parameter + Clone + PartialEq + 'static>(
+    parameter: P,
     to_processor: ReadSignal<Option<ToProcessorSender>>,
 ) -> Element {
     let id = parameter.id();
@@ -18,7 +22,15 @@ pub fn ParameterSlider(
     let name = use_hook(|| parameter.name().to_string());
 
     // Create a signal based on the parameter value so that we can respond to changes.
-    let mut value = use_signal(|| parameter.value);
+    let mut value = use_signal(|| parameter.user_value());
+
+    // The slider's step in its linear range, parameters with a single value have a single step.
+    let user_range = value_converter.max() - value_converter.min();
+    let linear_step = match parameter.step() {
+        Some(step) if user_range > 0.0 => (step / user_range) as f64,
+        Some(_) => 1.0,
+        None => 0.01,
+    };
 
     // Derive a linear value for use by the slider.
     let linear_value = use_memo({
@@ -53,11 +65,14 @@ pub fn ParameterSlider(
                 value: Some(SliderValue::Single(linear_value())),
                 min: 0.0,
                 max: 1.0,
-                step: 0.01,
+                step: linear_step,
                 default_value: SliderValue::Single(default_linear_value as f64),
                 on_value_change: move |new_value| match new_value{
                     SliderValue::Single(new_value) => {
-                        *value.write() = value_converter.linear_to_user(new_value as f32);
+                        // Let the parameter snap the value to its steps.
+                        let mut snapped = parameter.clone();
+                        snapped.set_user_value(value_converter.linear_to_user(new_value as f32));
+                        *value.write() = snapped.user_value();
                     }
                 },
                 label: name,
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use audio_module::{PushMessage, ToProcessor};
-use audio_stream::AudioStream;
+use audio_stream::{AudioStream, StreamConfig};
 use eframe::{
     Frame,
     egui::{self, Align, CentralPanel, Layout, RichText, TextStyle},
@@ -8,6 +8,9 @@ use eframe::{
 use freeverb_module::{FreeverbModule, FreeverbParameterId};
 use ui_egui::{FreeverbUi, FreeverbUiState};
 
+#[cfg(not(target_arch = "wasm32"))]
+use eframe::egui::TopBottomPanel;
+
 pub struct App {
     ui_state: FreeverbUiState,
     audio_stream: Option<AudioStream<FreeverbModule>>,
@@ -23,7 +26,7 @@ impl App {
 
     fn toggle_audio_stream(&mut self) {
         if self.audio_stream.is_none() {
-            match AudioStream::new() {
+            match AudioStream::new(StreamConfig::default()) {
                 Ok(stream) => {
                     self.ui_state.sample_rate = stream.sample_rate();
                     stream.to_processor().push(ToProcessor::SetParameter(
@@ -48,6 +51,43 @@ impl eframe::App for App {
             self.ui_state.receive_processor_messages(&from_processor);
         }
 
+        // Stream stats aren't available on the web
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(stream) = &self.audio_stream {
+            TopBottomPanel::bottom("status").show(ctx, |ui| {
+                let stats = stream.stats();
+
+                ui.horizontal(|ui| {
+                    match stats.latency {
+                        Some(latency) => {
+                            ui.label(format!("Latency: {:.1}ms", latency.as_secs_f64() * 1000.0))
+                        }
+                        None => ui.label("Stopped"),
+                    };
+                    ui.separator();
+                    ui.label(format!("DSP load: {}", stats.processor_load));
+                    ui.separator();
+                    ui.label(format!(
+                        "Xruns: input {}, output {}, server {}",
+                        stats.input_to_processor.xruns(),
+                        stats.processor_to_output.xruns(),
+                        stats.server_xruns
+                    ));
+                    if let Some(error) = &stats.last_error {
+                        ui.separator();
+                        ui.label(error);
+                    }
+
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        let mut recording = stream.is_recording();
+                        if ui.toggle_value(&mut recording, "Record").changed() {
+                            toggle_recording(stream);
+                        }
+                    });
+                });
+            });
+        }
+
         CentralPanel::default().show(ctx, |ui| {
             #[cfg(target_os = "android")]
             {
@@ -77,3 +117,22 @@ impl eframe::App for App {
         });
     }
 }
+
+/// Starts or stops recording the stream's output to a timestamped WAV file
+#[cfg(not(target_arch = "wasm32"))]
+fn toggle_recording(stream: &AudioStream<FreeverbModule>) {
+    if stream.is_recording() {
+        if let Err(error) = stream.stop_recording() {
+            println!("Failed to stop recording: {error}");
+        }
+    } else {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = format!("freeverb_{timestamp}.wav");
+        if let Err(error) = stream.start_recording(&path, false) {
+            println!("Failed to start recording: {error}");
+        }
+    }
+}
@@ -1,4 +1,8 @@
+mod module_ui;
 mod ui;
 mod widgets;
 
-pub use ui::{FreeverbUi, FreeverbUiState};
+pub use {
+    module_ui::GenericModuleUi,
+    ui::{FreeverbUi, FreeverbUiState},
+};
@@ -0,0 +1,83 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use crate::widgets::{Checkbox, EnumComboBox, FloatSlider, IntSlider};
+use audio_module::{AudioModule, Parameter, Parameters, PushMessage, ToProcessor, ValueType};
+use egui::{CollapsingHeader, Response, Ui, Widget};
+
+/// An editor for any `AudioModule`, with a control for each of the module's parameters
+///
+/// Parameters are shown in the order that they're provided by `Parameters::parameters`, with
+/// grouped parameters gathered under a header at the position of the group's first parameter.
+/// Hidden parameters are left out, see `Parameter::is_hidden`.
+pub struct GenericModuleUi<'a, M: AudioModule, T: PushMessage<ToProcessor>> {
+    parameters: &'a mut M::Parameters,
+    to_processor: &'a Option<T>,
+    _module: PhantomData<M>,
+}
+
+impl<'a, M: AudioModule, T: PushMessage<ToProcessor>> GenericModuleUi<'a, M, T> {
+    pub fn new(parameters: &'a mut M::Parameters, to_processor: &'a Option<T>) -> Self {
+        Self {
+            parameters,
+            to_processor,
+            _module: PhantomData,
+        }
+    }
+}
+
+impl<'a, M: AudioModule, T: PushMessage<ToProcessor>> Widget for GenericModuleUi<'a, M, T> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        // Gather the parameters into groups, keeping the order of each group's first parameter
+        let mut groups: Vec<ParameterGroup> = Vec::new();
+        for parameter in self.parameters.parameters_mut() {
+            if parameter.is_hidden() {
+                continue;
+            }
+
+            let name = parameter.group();
+            match groups.iter_mut().find(|group| group.name == name) {
+                Some(group) => group.parameters.push(parameter),
+                None => groups.push(ParameterGroup {
+                    name,
+                    parameters: vec![parameter],
+                }),
+            }
+        }
+
+        ui.vertical(|ui| {
+            for group in groups {
+                match group.name {
+                    Some(name) => {
+                        CollapsingHeader::new(name.as_str())
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                parameter_controls(ui, group.parameters, self.to_processor)
+                            });
+                    }
+                    None => parameter_controls(ui, group.parameters, self.to_processor),
+                }
+            }
+        })
+        .response
+    }
+}
+
+struct ParameterGroup<'a> {
+    name: Option<Arc<String>>,
+    parameters: Vec<&'a mut dyn Parameter>,
+}
+
+fn parameter_controls<T: PushMessage<ToProcessor>>(
+    ui: &mut Ui,
+    parameters: Vec<&mut dyn Parameter>,
+    to_processor: &Option<T>,
+) {
+    for parameter in parameters {
+        match parameter.value_type() {
+            ValueType::Float => ui.add(FloatSlider::new(parameter, to_processor)),
+            ValueType::Bool => ui.add(Checkbox::new(parameter, to_processor)),
+            ValueType::Int => ui.add(IntSlider::new(parameter, to_processor)),
+            ValueType::Enum => ui.add(EnumComboBox::new(parameter, to_processor)),
+        };
+    }
+}
@@ -1,9 +1,9 @@
 use std::collections::VecDeque;
 
-use crate::widgets::{Checkbox, FloatSlider, PhaseScope};
+use crate::{GenericModuleUi, widgets::PhaseScope};
 use audio_module::{PopMessage, PushMessage, ToProcessor};
 use egui::{Response, Ui, Widget, vec2};
-use freeverb_module::{FreeverbParameters, FromFreeverb};
+use freeverb_module::{FreeverbModule, FreeverbParameters, FromFreeverb};
 
 #[derive(Default)]
 pub struct FreeverbUiState {
@@ -53,20 +53,10 @@ impl<'a, T: PushMessage<ToProcessor>> Widget for FreeverbUi<'a, T> {
 
         let contents = |ui: &mut Ui| {
             // Parameters
-            ui.vertical(|ui| {
-                ui.add(FloatSlider::new(
-                    &mut parameters.dampening,
-                    &self.to_processor,
-                ));
-                ui.add(FloatSlider::new(&mut parameters.width, &self.to_processor));
-                ui.add(FloatSlider::new(
-                    &mut parameters.room_size,
-                    &self.to_processor,
-                ));
-                ui.add(Checkbox::new(&mut parameters.freeze, &self.to_processor));
-                ui.add(FloatSlider::new(&mut parameters.dry, &self.to_processor));
-                ui.add(FloatSlider::new(&mut parameters.wet, &self.to_processor));
-            });
+            ui.add(GenericModuleUi::<FreeverbModule, _>::new(
+                parameters,
+                &self.to_processor,
+            ));
 
             // Scope
             ui.vertical(|ui| {
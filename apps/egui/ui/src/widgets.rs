@@ -1,5 +1,10 @@
 mod check_box;
+mod enum_combo_box;
 mod float_slider;
+mod int_slider;
 mod phase_scope;
 
-pub use self::{check_box::Checkbox, float_slider::FloatSlider, phase_scope::PhaseScope};
+pub use self::{
+    check_box::Checkbox, enum_combo_box::EnumComboBox, float_slider::FloatSlider,
+    int_slider::IntSlider, phase_scope::PhaseScope,
+};
@@ -1,13 +1,13 @@
-use audio_module::{BoolParameter, Parameter, PushMessage, ToProcessor};
+use audio_module::{Parameter, ParameterValue, PushMessage, ToProcessor};
 use egui::{Response, Ui, Widget};
 
 pub struct Checkbox<'a, T: PushMessage<ToProcessor>> {
-    parameter: &'a mut BoolParameter,
+    parameter: &'a mut dyn Parameter,
     to_processor: &'a Option<T>,
 }
 
 impl<'a, T: PushMessage<ToProcessor>> Checkbox<'a, T> {
-    pub fn new(parameter: &'a mut BoolParameter, to_processor: &'a Option<T>) -> Self {
+    pub fn new(parameter: &'a mut dyn Parameter, to_processor: &'a Option<T>) -> Self {
         Self {
             parameter,
             to_processor,
@@ -17,16 +17,14 @@ impl<'a, T: PushMessage<ToProcessor>> Checkbox<'a, T> {
 
 impl<'a, T: PushMessage<ToProcessor>> Widget for Checkbox<'a, T> {
     fn ui(self, ui: &mut Ui) -> Response {
-        let mut value = self.parameter.value;
+        let mut value = self.parameter.user_value() != 0.0;
         let response = ui.checkbox(&mut value, self.parameter.name().as_str());
 
         if response.changed() {
-            self.parameter.value = value;
+            let value = ParameterValue::Bool(value).as_f32();
+            self.parameter.set_user_value(value);
             if let Some(commands) = self.to_processor {
-                commands.push(ToProcessor::SetParameter(
-                    self.parameter.id(),
-                    if value { 1.0 } else { 0.0 },
-                ));
+                commands.push(ToProcessor::SetParameter(self.parameter.id(), value));
             }
         }
 
@@ -0,0 +1,51 @@
+use audio_module::{Parameter, PushMessage, ToProcessor};
+use egui::{self, Response, Ui, Widget};
+
+pub struct EnumComboBox<'a, T: PushMessage<ToProcessor>> {
+    parameter: &'a mut dyn Parameter,
+    to_processor: &'a Option<T>,
+}
+
+impl<'a, T: PushMessage<ToProcessor>> EnumComboBox<'a, T> {
+    pub fn new(parameter: &'a mut dyn Parameter, to_processor: &'a Option<T>) -> Self {
+        Self {
+            parameter,
+            to_processor,
+        }
+    }
+}
+
+impl<'a, T: PushMessage<ToProcessor>> Widget for EnumComboBox<'a, T> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.vertical(|ui| {
+            let labels = self.parameter.variant_labels().unwrap_or_default();
+            let id = self.parameter.id();
+
+            ui.label(self.parameter.name().as_str());
+
+            let previous = self.parameter.user_value() as usize;
+            let mut selected = previous;
+
+            let mut response = egui::ComboBox::from_id_salt(("enum_parameter", id))
+                .selected_text(labels.get(selected).map_or("", String::as_str))
+                .show_ui(ui, |ui| {
+                    for (variant, label) in labels.iter().enumerate() {
+                        ui.selectable_value(&mut selected, variant, label.as_str());
+                    }
+                })
+                .response;
+
+            if selected != previous {
+                self.parameter.set_user_value(selected as f32);
+                response.mark_changed();
+
+                if let Some(to_processor) = self.to_processor {
+                    to_processor.push(ToProcessor::SetParameter(id, self.parameter.user_value()));
+                }
+            }
+
+            response
+        })
+        .inner
+    }
+}
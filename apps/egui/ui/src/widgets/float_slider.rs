@@ -1,13 +1,13 @@
-use audio_module::{FloatParameter, Parameter, PushMessage, ToProcessor};
+use audio_module::{Parameter, PushMessage, ToProcessor};
 use egui::{self, Response, Ui, Widget};
 
 pub struct FloatSlider<'a, T: PushMessage<ToProcessor>> {
-    parameter: &'a mut FloatParameter,
+    parameter: &'a mut dyn Parameter,
     to_processor: &'a Option<T>,
 }
 
 impl<'a, T: PushMessage<ToProcessor>> FloatSlider<'a, T> {
-    pub fn new(parameter: &'a mut FloatParameter, to_processor: &'a Option<T>) -> Self {
+    pub fn new(parameter: &'a mut dyn Parameter, to_processor: &'a Option<T>) -> Self {
         Self {
             parameter,
             to_processor,
@@ -24,13 +24,12 @@ impl<'a, T: PushMessage<ToProcessor>> Widget for FloatSlider<'a, T> {
 
             ui.label(self.parameter.name().as_str());
 
+            let mut value = self.parameter.user_value();
             let response = ui.add({
-                let mut slider = egui::Slider::new(
-                    &mut self.parameter.value,
-                    value_converter.min()..=value_converter.max(),
-                )
-                .custom_formatter(|n, _| string_converter.to_string(n as f32))
-                .custom_parser(|s| string_converter.to_f32(s).map(|n| n as f64));
+                let mut slider =
+                    egui::Slider::new(&mut value, value_converter.min()..=value_converter.max())
+                        .custom_formatter(|n, _| string_converter.to_string(n as f32))
+                        .custom_parser(|s| string_converter.to_f32(s).map(|n| n as f64));
 
                 if let Some(unit) = string_converter.unit() {
                     slider = slider.suffix(format!(" {unit}"));
@@ -39,13 +38,17 @@ impl<'a, T: PushMessage<ToProcessor>> Widget for FloatSlider<'a, T> {
                 slider
             });
 
+            if response.changed() {
+                self.parameter.set_user_value(value);
+            }
+
             if let Some(to_processor) = self.to_processor {
                 if response.drag_started() {
                     to_processor.push(ToProcessor::BeginEdit(id));
                 }
 
                 if response.changed() {
-                    to_processor.push(ToProcessor::SetParameter(id, self.parameter.value));
+                    to_processor.push(ToProcessor::SetParameter(id, value));
                 }
 
                 if response.drag_stopped() {
@@ -0,0 +1,67 @@
+use audio_module::{Parameter, PushMessage, ToProcessor};
+use egui::{self, Response, Ui, Widget};
+
+pub struct IntSlider<'a, T: PushMessage<ToProcessor>> {
+    parameter: &'a mut dyn Parameter,
+    to_processor: &'a Option<T>,
+}
+
+impl<'a, T: PushMessage<ToProcessor>> IntSlider<'a, T> {
+    pub fn new(parameter: &'a mut dyn Parameter, to_processor: &'a Option<T>) -> Self {
+        Self {
+            parameter,
+            to_processor,
+        }
+    }
+}
+
+impl<'a, T: PushMessage<ToProcessor>> Widget for IntSlider<'a, T> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.vertical(|ui| {
+            let value_converter = self.parameter.value_converter();
+            let string_converter = self.parameter.string_converter();
+            let step = self.parameter.step().unwrap_or(1.0);
+            let id = self.parameter.id();
+
+            ui.label(self.parameter.name().as_str());
+
+            let mut value = self.parameter.user_value().round() as i32;
+            let response = ui.add({
+                let mut slider = egui::Slider::new(
+                    &mut value,
+                    value_converter.min() as i32..=value_converter.max() as i32,
+                )
+                .step_by(step as f64)
+                .custom_formatter(|n, _| string_converter.to_string(n as f32))
+                .custom_parser(|s| string_converter.to_f32(s).map(|n| n as f64));
+
+                if let Some(unit) = string_converter.unit() {
+                    slider = slider.suffix(format!(" {unit}"));
+                }
+
+                slider
+            });
+
+            if response.changed() {
+                self.parameter.set_user_value(value as f32);
+            }
+
+            if let Some(to_processor) = self.to_processor {
+                if response.drag_started() {
+                    to_processor.push(ToProcessor::BeginEdit(id));
+                }
+
+                if response.changed() {
+                    to_processor.push(ToProcessor::SetParameter(id, self.parameter.user_value()));
+                }
+
+                if response.drag_stopped() {
+                    to_processor.push(ToProcessor::EndEdit(id));
+                }
+            }
+
+            response
+        })
+        .response
+    }
+}
@@ -11,10 +11,31 @@ function on_wasm_loaded() {
   let handle = new wasm_bindgen.WebHandle();
   handle.start(document.getElementById("egui_canvas"))
     .then(on_app_started)
-    .then(wasm_bindgen.initialize_audio())
+    .then(initialize_audio)
     .catch(on_error);
 }
 
+// Audio failures are logged rather than treated as app failures, e.g. if mic access is denied.
+function initialize_audio() {
+  wasm_bindgen.initialize_audio(input_device_id(), false)
+    .then(log_input_devices)
+    .catch((error) => console.error("Failed to initialize audio: " + error));
+}
+
+// The input device can be chosen with a `?input=<deviceId>` query parameter,
+// the available devices are logged once audio has been initialized.
+function input_device_id() {
+  return new URLSearchParams(window.location.search).get("input") ?? undefined;
+}
+
+function log_input_devices() {
+  wasm_bindgen.available_input_devices().then((devices) => {
+    for (const device of devices) {
+      console.info(`Audio input: ${device.label} (?input=${device.deviceId})`);
+    }
+  });
+}
+
 function on_app_started() {
   console.debug("App started.");
   document.getElementById("center_text").innerHTML = '';
@@ -39,12 +39,38 @@ impl WebHandle {
     }
 }
 
+/// Initializes audio, using the input device with the given id if one is provided
+///
+/// The input device ids are available from [available_input_devices].
 #[wasm_bindgen]
-pub async fn initialize_audio() -> Result<(), wasm_bindgen::JsValue> {
+pub async fn initialize_audio(
+    input_device_id: Option<String>,
+    output_only: bool,
+) -> Result<(), wasm_bindgen::JsValue> {
     audio_stream::initialize_audio(
         "./freeverb_bg.wasm",
         "./freeverb.js",
         "./audio_stream_worklet.js",
+        audio_stream::AudioOptions {
+            input_device_id,
+            output_only,
+            ..Default::default()
+        },
     )
     .await
 }
+
+/// Returns the available audio input devices as an array of `{ deviceId, label }` objects
+#[wasm_bindgen]
+pub async fn available_input_devices() -> Result<js_sys::Array, wasm_bindgen::JsValue> {
+    let result = js_sys::Array::new();
+
+    for device in audio_stream::available_input_devices().await? {
+        let info = js_sys::Object::new();
+        js_sys::Reflect::set(&info, &"deviceId".into(), &device.device_id.into())?;
+        js_sys::Reflect::set(&info, &"label".into(), &device.label.into())?;
+        result.push(&info);
+    }
+
+    Ok(result)
+}
@@ -4,9 +4,9 @@ use std::{
     time::Duration,
 };
 
-use crate::widgets::{PhaseScope, freeverb_parameters};
-use audio_module::{PopMessage, PushMessage, ToProcessor};
-use audio_stream::AudioStream;
+use crate::widgets::{PhaseScope, module_parameters};
+use audio_module::{ParameterValue, Parameters, PopMessage, PushMessage, ToProcessor};
+use audio_stream::{AudioStream, StreamConfig};
 use freeverb_module::{FreeverbModule, FreeverbParameterId, FreeverbParameters, FromFreeverb};
 use iced::{
     Element, Subscription,
@@ -41,13 +41,15 @@ impl App {
             row![
                 text("Freeverb").size(40),
                 horizontal_space(),
+                text(self.processor_load_text()),
                 checkbox("Audio Enabled", self.audio_stream.is_some())
                     .on_toggle(Message::SetEnabled),
             ]
+            .spacing(20)
             .align_y(Vertical::Center),
             horizontal_rule(1),
             row![
-                freeverb_parameters(&self.parameters),
+                module_parameters(&self.parameters),
                 vertical_rule(1),
                 PhaseScope::new(self.audio_stream.is_some(), self.scope_frames.clone()),
             ]
@@ -62,7 +64,7 @@ impl App {
         match message {
             Message::SetEnabled(enabled) => {
                 if enabled {
-                    match AudioStream::new() {
+                    match AudioStream::new(StreamConfig::default()) {
                         Ok(stream) => {
                             self.sample_rate = stream.sample_rate();
                             stream.to_processor().push(ToProcessor::SetParameter(
@@ -79,33 +81,15 @@ impl App {
                     self.scope_frames.lock().unwrap().clear();
                 }
             }
-            Message::SetFloat(id, value) => {
-                match id {
-                    FreeverbParameterId::Dampening => self.parameters.dampening.value = value,
-                    FreeverbParameterId::Width => self.parameters.width.value = value,
-                    FreeverbParameterId::RoomSize => self.parameters.room_size.value = value,
-                    FreeverbParameterId::Dry => self.parameters.dry.value = value,
-                    FreeverbParameterId::Wet => self.parameters.wet.value = value,
-                    _ => unreachable!(),
-                }
+            Message::SetParameter(id, value) => {
+                self.parameters.set_value(id, value);
 
-                if let Some(stream) = &self.audio_stream {
+                // Stepped parameters round the value, so the parameter's value is sent on
+                if let (Some(stream), Some(value)) = (&self.audio_stream, self.parameters.value(id))
+                {
                     stream
                         .to_processor()
-                        .push(ToProcessor::SetParameter(id as usize, value));
-                }
-            }
-            Message::SetBool(id, value) => {
-                match id {
-                    FreeverbParameterId::Freeze => self.parameters.freeze.value = value,
-                    _ => unreachable!(),
-                }
-
-                if let Some(stream) = &self.audio_stream {
-                    stream.to_processor().push(ToProcessor::SetParameter(
-                        id as usize,
-                        if value { 1.0 } else { 0.0 },
-                    ));
+                        .push(ToProcessor::set_value(id, value));
                 }
             }
             Message::FromProcessor(message) => match message {
@@ -124,6 +108,13 @@ impl App {
         }
     }
 
+    fn processor_load_text(&self) -> String {
+        match &self.audio_stream {
+            Some(stream) => format!("DSP load: {}", stream.processor_load()),
+            None => String::new(),
+        }
+    }
+
     /// Sets up a subscription to receive messages coming from the processor
     pub fn subscription(&self) -> Subscription<Message> {
         let Some(audio_stream) = &self.audio_stream else {
@@ -151,7 +142,6 @@ impl App {
 #[allow(clippy::large_enum_variant)]
 pub enum Message {
     SetEnabled(bool),
-    SetFloat(FreeverbParameterId, f32),
-    SetBool(FreeverbParameterId, bool),
+    SetParameter(usize, ParameterValue),
     FromProcessor(FromFreeverb),
 }
@@ -1,33 +1,35 @@
 mod phase_scope;
 
 use crate::app::Message;
-use audio_module::{BoolParameter, FloatParameter, Parameter};
-use freeverb_module::{FreeverbParameterId, FreeverbParameters};
-use iced::widget::{checkbox, column, container, row, slider, text};
+use audio_module::{Parameter, ParameterValue, Parameters, ValueType};
+use iced::widget::{Column, checkbox, column, container, pick_list, row, slider, text};
 use iced::{Alignment, Element, Fill};
 
 pub use self::phase_scope::PhaseScope;
 
-pub fn freeverb_parameters(parameters: &FreeverbParameters) -> Element<'_, Message> {
-    column![
-        parameter_slider(&parameters.dampening, FreeverbParameterId::Dampening),
-        parameter_slider(&parameters.width, FreeverbParameterId::Width),
-        parameter_slider(&parameters.room_size, FreeverbParameterId::RoomSize),
-        parameter_toggle(&parameters.freeze, FreeverbParameterId::Freeze),
-        parameter_slider(&parameters.dry, FreeverbParameterId::Dry),
-        parameter_slider(&parameters.wet, FreeverbParameterId::Wet),
-    ]
+/// Shows a control for each of a module's parameters, apart from hidden parameters
+pub fn module_parameters(parameters: &impl Parameters) -> Element<'_, Message> {
+    Column::with_children(
+        parameters
+            .parameters()
+            .into_iter()
+            .filter(|parameter| !parameter.is_hidden())
+            .map(|parameter| match parameter.value_type() {
+                ValueType::Float => parameter_slider(parameter),
+                ValueType::Bool => parameter_toggle(parameter),
+                ValueType::Int => parameter_int_slider(parameter),
+                ValueType::Enum => parameter_pick_list(parameter),
+            }),
+    )
     .width(300.0)
     .spacing(20)
     .into()
 }
 
-pub fn parameter_slider(
-    parameter: &FloatParameter,
-    id: FreeverbParameterId,
-) -> Element<'_, Message> {
+pub fn parameter_slider(parameter: &dyn Parameter) -> Element<'_, Message> {
+    let id = parameter.id();
     let name = parameter.name();
-    let value = parameter.value;
+    let value = parameter.user_value();
     let string_converter = parameter.string_converter();
     let value_converter = parameter.value_converter();
     let default_value = value_converter.user_to_linear(parameter.default_user_value());
@@ -36,7 +38,7 @@ pub fn parameter_slider(
 
     let slider = container(
         slider(0.0..=1.0, value, move |new_value| {
-            Message::SetFloat(id, value_converter.linear_to_user(new_value))
+            Message::SetParameter(id, value_converter.linear_to_user(new_value).into())
         })
         .default(default_value)
         .step(0.01)
@@ -53,14 +55,85 @@ pub fn parameter_slider(
         .into()
 }
 
-pub fn parameter_toggle(
-    parameter: &BoolParameter,
-    id: FreeverbParameterId,
-) -> Element<'_, Message> {
+pub fn parameter_toggle(parameter: &dyn Parameter) -> Element<'_, Message> {
+    let id = parameter.id();
     let name = parameter.name().to_string();
-    let value = parameter.value;
+    let value = parameter.user_value() != 0.0;
 
     checkbox(name, value)
-        .on_toggle(move |new_value| Message::SetBool(id, new_value))
+        .on_toggle(move |new_value| Message::SetParameter(id, new_value.into()))
         .into()
 }
+
+pub fn parameter_int_slider(parameter: &dyn Parameter) -> Element<'_, Message> {
+    let id = parameter.id();
+    let name = parameter.name();
+    let value = parameter.user_value();
+    let string_converter = parameter.string_converter();
+    let value_converter = parameter.value_converter();
+
+    let caption = text(name.to_string());
+
+    let slider = container(
+        slider(
+            value_converter.min()..=value_converter.max(),
+            value,
+            move |new_value| Message::SetParameter(id, ParameterValue::Int(new_value as i32)),
+        )
+        .default(parameter.default_user_value())
+        .step(parameter.step().unwrap_or(1.0)),
+    );
+
+    let value_text = text(string_converter.to_string_with_unit(value));
+    let slider_with_value = row![slider, value_text].width(Fill).spacing(10);
+
+    column![caption, slider_with_value]
+        .width(Fill)
+        .align_x(Alignment::Start)
+        .spacing(10)
+        .into()
+}
+
+pub fn parameter_pick_list(parameter: &dyn Parameter) -> Element<'_, Message> {
+    let id = parameter.id();
+    let name = parameter.name();
+
+    // Each option carries its variant's index, so selections don't need to be mapped back from
+    // their labels
+    let variants: Vec<EnumVariant> = parameter
+        .variant_labels()
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .map(|(index, label)| EnumVariant {
+            index,
+            label: label.clone(),
+        })
+        .collect();
+    let selected = variants.get(parameter.user_value() as usize).cloned();
+
+    let caption = text(name.to_string());
+
+    let pick_list = pick_list(variants, selected, move |variant| {
+        Message::SetParameter(id, ParameterValue::Enum(variant.index))
+    });
+
+    column![caption, pick_list]
+        .width(Fill)
+        .align_x(Alignment::Start)
+        .spacing(10)
+        .into()
+}
+
+/// An option in an enum parameter's pick list
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct EnumVariant {
+    index: usize,
+    label: String,
+}
+
+impl std::fmt::Display for EnumVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.label)
+    }
+}
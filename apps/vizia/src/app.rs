@@ -1,6 +1,6 @@
-use crate::widgets::{PhaseScope, ScopeFrames, parameter_slider, parameter_toggle};
-use audio_module::{PopMessage, PushMessage, ToProcessor};
-use audio_stream::AudioStream;
+use crate::widgets::{PhaseScope, ScopeFrames, module_parameters};
+use audio_module::{ParameterValue, Parameters, PopMessage, PushMessage, ToProcessor};
+use audio_stream::{AudioStream, StreamConfig};
 use freeverb_module::{FreeverbModule, FreeverbParameterId, FreeverbParameters, FromFreeverb};
 use vizia::prelude::*;
 
@@ -10,9 +10,10 @@ pub const UI_SIZE: (u32, u32) = (700, 450);
 #[allow(clippy::large_enum_variant)]
 pub enum Message {
     EnabledChanged,
-    SetFloat(FreeverbParameterId, f32),
-    ToggleBool(FreeverbParameterId),
+    SetParameter(usize, ParameterValue),
+    ToggleBool(usize),
     FromProcessor(FromFreeverb),
+    UpdateProcessorLoad,
 }
 
 #[derive(Lens)]
@@ -22,6 +23,7 @@ pub struct App {
     update_timer: Option<Timer>,
     sample_rate: usize,
     scope_frames: ScopeFrames,
+    processor_load: String,
 }
 
 impl App {
@@ -32,6 +34,7 @@ impl App {
             sample_rate: 0,
             scope_frames: ScopeFrames::with_capacity(1024),
             update_timer: None,
+            processor_load: String::new(),
         }
         .build(cx);
 
@@ -41,6 +44,8 @@ impl App {
             HStack::new(cx, |cx| {
                 Label::new(cx, "Freeverb").font_size(40.0);
 
+                Label::new(cx, Self::processor_load);
+
                 ToggleButton::new(cx, audio_enabled, {
                     move |cx| Label::new(cx, "Audio Enabled")
                 })
@@ -52,16 +57,9 @@ impl App {
             Divider::horizontal(cx);
 
             HStack::new(cx, |cx| {
-                VStack::new(cx, |cx| {
-                    parameter_slider(cx, Self::parameters.map_ref(|p| &p.dampening));
-                    parameter_slider(cx, Self::parameters.map_ref(|p| &p.width));
-                    parameter_slider(cx, Self::parameters.map_ref(|p| &p.room_size));
-                    parameter_toggle(cx, Self::parameters.map_ref(|p| &p.freeze));
-                    parameter_slider(cx, Self::parameters.map_ref(|p| &p.dry));
-                    parameter_slider(cx, Self::parameters.map_ref(|p| &p.wet));
-                })
-                .width(Pixels(300.0))
-                .vertical_gap(Pixels(20.0));
+                VStack::new(cx, |cx| module_parameters(cx, Self::parameters))
+                    .width(Pixels(300.0))
+                    .vertical_gap(Pixels(20.0));
 
                 Divider::vertical(cx);
 
@@ -80,7 +78,7 @@ impl Model for App {
         event.map(|message, _meta| match message {
             Message::EnabledChanged => {
                 if self.audio_stream.is_none() {
-                    match AudioStream::new() {
+                    match AudioStream::new(StreamConfig::default()) {
                         Ok(stream) => {
                             self.sample_rate = stream.sample_rate();
                             stream.to_processor().push(ToProcessor::SetParameter(
@@ -99,6 +97,7 @@ impl Model for App {
                                     while let Some(message) = from_processor.pop() {
                                         cx.emit(Message::FromProcessor(message));
                                     }
+                                    cx.emit(Message::UpdateProcessorLoad);
                                 },
                             );
                             cx.start_timer(update_timer);
@@ -112,36 +111,32 @@ impl Model for App {
                     }
                     self.audio_stream = None;
                     self.scope_frames.clear();
+                    self.processor_load.clear();
                 }
             }
-            Message::SetFloat(id, value) => {
-                match id {
-                    FreeverbParameterId::Dampening => self.parameters.dampening.value = *value,
-                    FreeverbParameterId::Width => self.parameters.width.value = *value,
-                    FreeverbParameterId::RoomSize => self.parameters.room_size.value = *value,
-                    FreeverbParameterId::Dry => self.parameters.dry.value = *value,
-                    FreeverbParameterId::Wet => self.parameters.wet.value = *value,
-                    _ => unreachable!(),
-                }
+            Message::SetParameter(id, value) => {
+                self.parameters.set_value(*id, *value);
 
-                if let Some(stream) = &self.audio_stream {
+                // Stepped parameters round the value, so the parameter's value is sent on
+                if let (Some(stream), Some(value)) =
+                    (&self.audio_stream, self.parameters.value(*id))
+                {
                     stream
                         .to_processor()
-                        .push(ToProcessor::SetParameter(*id as usize, *value));
+                        .push(ToProcessor::set_value(*id, value));
                 }
             }
             Message::ToggleBool(id) => {
-                let value = match id {
-                    FreeverbParameterId::Freeze => &mut self.parameters.freeze.value,
-                    _ => unreachable!(),
+                let Some(ParameterValue::Bool(value)) = self.parameters.value(*id) else {
+                    return;
                 };
 
-                *value = !*value;
+                self.parameters.set_value(*id, !value);
 
                 if let Some(stream) = &self.audio_stream {
                     stream.to_processor().push(ToProcessor::SetParameter(
-                        *id as usize,
-                        if *value { 1.0 } else { 0.0 },
+                        *id,
+                        ParameterValue::Bool(!value).as_f32(),
                     ));
                 }
             }
@@ -157,6 +152,11 @@ impl Model for App {
                     self.scope_frames.drain(0..frames_to_drop);
                 }
             },
+            Message::UpdateProcessorLoad => {
+                if let Some(stream) = &self.audio_stream {
+                    self.processor_load = format!("DSP load: {}", stream.processor_load());
+                }
+            }
         });
     }
 }
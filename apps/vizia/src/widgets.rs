@@ -1,21 +1,51 @@
 mod phase_scope;
 
 use crate::app::Message;
-use audio_module::{BoolParameter, FloatParameter, Parameter};
+use audio_module::{Parameter, ParameterValue, Parameters, ValueType};
 use vizia::prelude::*;
 
 pub use phase_scope::{PhaseScope, ScopeFrames};
 
-pub fn parameter_slider(cx: &mut Context, parameter_lens: impl Lens<Target = FloatParameter>) {
-    let parameter = parameter_lens.get(cx);
+/// Adds a control for each of a module's parameters, apart from hidden parameters
+pub fn module_parameters<P>(cx: &mut Context, parameters_lens: impl Lens<Target = P>)
+where
+    P: Parameters + Clone + 'static,
+{
+    let parameters = parameters_lens.get(cx);
+    for parameter in parameters.parameters() {
+        if parameter.is_hidden() {
+            continue;
+        }
+
+        match parameter.value_type() {
+            // Ints and enums use sliders that snap to the parameter's steps
+            ValueType::Float | ValueType::Int | ValueType::Enum => {
+                parameter_slider(cx, parameters_lens.clone(), parameter)
+            }
+            ValueType::Bool => parameter_toggle(cx, parameters_lens.clone(), parameter),
+        }
+    }
+}
+
+pub fn parameter_slider<P>(
+    cx: &mut Context,
+    parameters_lens: impl Lens<Target = P>,
+    parameter: &dyn Parameter,
+) where
+    P: Parameters + 'static,
+{
     let name = parameter.name();
     let id = parameter.id();
+    let value_type = parameter.value_type();
     let value_converter = parameter.value_converter();
     let string_converter = parameter.string_converter();
 
-    let value_lens = parameter_lens.map({
+    let value_lens = parameters_lens.map({
         let value_converter = value_converter.clone();
-        move |p| value_converter.user_to_linear(p.value)
+        move |parameters| {
+            let value = parameters.value(id).map_or(0.0, |value| value.as_f32());
+            value_converter.user_to_linear(value)
+        }
     });
 
     VStack::new(cx, |cx| {
@@ -25,10 +55,13 @@ pub fn parameter_slider(cx: &mut Context, parameter_lens: impl Lens<Target = Flo
         // Add a slider with its value to its right.
         HStack::new(cx, |cx| {
             Slider::new(cx, value_lens).on_change(move |cx, value| {
-                cx.emit(Message::SetFloat(
-                    id.try_into().unwrap(),
-                    value_converter.linear_to_user(value),
-                ))
+                let value = value_converter.linear_to_user(value);
+                let value = match value_type {
+                    ValueType::Int => ParameterValue::Int(value.round() as i32),
+                    ValueType::Enum => ParameterValue::Enum(value.round() as usize),
+                    _ => ParameterValue::Float(value),
+                };
+                cx.emit(Message::SetParameter(id, value))
             });
             Label::new(
                 cx,
@@ -47,16 +80,26 @@ pub fn parameter_slider(cx: &mut Context, parameter_lens: impl Lens<Target = Flo
     .vertical_gap(Pixels(5.0));
 }
 
-pub fn parameter_toggle(cx: &mut Context, parameter_lens: impl Lens<Target = BoolParameter>) {
-    let parameter = parameter_lens.get(cx);
+pub fn parameter_toggle<P>(
+    cx: &mut Context,
+    parameters_lens: impl Lens<Target = P>,
+    parameter: &dyn Parameter,
+) where
+    P: Parameters + 'static,
+{
     let id = parameter.id();
+    let value_lens = parameters_lens.map(move |parameters| {
+        parameters
+            .value(id)
+            .is_some_and(|value| value.as_f32() != 0.0)
+    });
 
     HStack::new(cx, |cx| {
-        ToggleButton::new(cx, parameter_lens.map_ref(|p| &p.value), {
+        ToggleButton::new(cx, value_lens, {
             let name = parameter.name().to_string();
             move |cx| Label::new(cx, &name)
         })
-        .on_toggle(move |cx| cx.emit(Message::ToggleBool(id.try_into().unwrap())));
+        .on_toggle(move |cx| cx.emit(Message::ToggleBool(id)));
     })
     .size(Auto)
     .horizontal_gap(Pixels(10.0))
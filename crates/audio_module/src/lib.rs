@@ -2,6 +2,8 @@ mod message;
 mod module;
 mod parameter;
 mod processor;
+mod smoothing;
+mod state;
 mod string_converter;
 mod value_converter;
 
@@ -10,6 +12,8 @@ pub use {
     module::{AudioModule, Parameters},
     parameter::*,
     processor::AudioProcessor,
+    smoothing::{SmoothedValue, Smoothing},
+    state::{ModuleState, StateError},
     string_converter::*,
     value_converter::*,
 };
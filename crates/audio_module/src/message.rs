@@ -1,10 +1,23 @@
+use crate::ParameterValue;
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ToProcessor {
     BeginEdit(usize),
+    /// Sets a parameter's user-facing value, see `ParameterValue::as_f32`
     SetParameter(usize, f32),
     EndEdit(usize),
 }
 
+impl ToProcessor {
+    /// Creates a `SetParameter` message for a typed value
+    ///
+    /// Bools are sent as `0.0` or `1.0`, ints as their value, and enums as the selected index.
+    pub fn set_value(id: usize, value: impl Into<ParameterValue>) -> Self {
+        Self::SetParameter(id, value.into().as_f32())
+    }
+}
+
 pub trait PushMessage<T: Send> {
     /// Pushes a message, exiting immediately if the channel is full.
     ///
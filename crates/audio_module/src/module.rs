@@ -1,12 +1,98 @@
-use crate::AudioProcessor;
+use crate::{AudioProcessor, ModuleState, Parameter, ParameterValue};
 
 pub trait AudioModule {
     const NAME: &'static str;
 
+    /// Whether or not the processor needs audio input
+    ///
+    /// Modules that generate audio without any input (e.g. synthesizers) can set this to `false`,
+    /// streams for the module will then run without an input device.
+    const REQUIRES_INPUT: bool = true;
+
+    /// The number of modules that this module is composed of
+    ///
+    /// This is only overridden by modules that host other modules, e.g. `ModuleChain` in
+    /// `audio_stream`, which gives each of its modules its own range of parameter ids.
+    const MODULES: usize = 1;
+
+    /// The version of the module's parameters
+    ///
+    /// This is saved along with the parameter values in a [ModuleState](crate::ModuleState), and
+    /// should be incremented when the meaning of a parameter's key or value changes, along with
+    /// updating [AudioModule::migrate_state].
+    const VERSION: u32 = 1;
+
     type Parameters: Parameters;
     type Processor: AudioProcessor;
 
     fn create_processor(sample_rate: usize) -> Self::Processor;
+
+    /// Updates state that was saved by an earlier version of the module, see [AudioModule::VERSION]
+    ///
+    /// This is called by `ModuleState::apply` before the saved values are applied, with the
+    /// state's version set to the version that it was saved with. Keys can be renamed and values
+    /// rescaled in place. The default implementation leaves the state unchanged, which is
+    /// sufficient when parameters have only been added or removed.
+    fn migrate_state(_state: &mut ModuleState) {}
 }
 
-pub trait Parameters: Default {}
+/// A module's set of parameters
+///
+/// Each parameter has a stable id that matches the id used in `ToProcessor` messages, which
+/// allows UIs and hosts to be generated for any module.
+pub trait Parameters: Default {
+    /// All of the module's parameters, in the order they should be presented to the user
+    ///
+    /// Hidden parameters are included so that every id can be looked up, UIs should skip them,
+    /// see [Parameter::is_hidden].
+    fn parameters(&self) -> Vec<&dyn Parameter>;
+
+    /// Mutable access to the module's parameters, in the same order as [Parameters::parameters]
+    fn parameters_mut(&mut self) -> Vec<&mut dyn Parameter>;
+
+    /// Returns the parameter with the given id
+    fn parameter(&self, id: usize) -> Option<&dyn Parameter> {
+        self.parameters()
+            .into_iter()
+            .find(|parameter| parameter.id() == id)
+    }
+
+    /// Returns the parameter with the given id for modification
+    fn parameter_mut(&mut self, id: usize) -> Option<&mut dyn Parameter> {
+        self.parameters_mut()
+            .into_iter()
+            .find(|parameter| parameter.id() == id)
+    }
+
+    /// Returns the parameter with the given key, see [Parameter::key]
+    fn parameter_by_key(&self, key: &str) -> Option<&dyn Parameter> {
+        self.parameters()
+            .into_iter()
+            .find(|parameter| parameter.key().as_str() == key)
+    }
+
+    /// Returns the parameter with the given key for modification
+    fn parameter_by_key_mut(&mut self, key: &str) -> Option<&mut dyn Parameter> {
+        self.parameters_mut()
+            .into_iter()
+            .find(|parameter| parameter.key().as_str() == key)
+    }
+
+    /// Returns the current value of the parameter with the given id
+    fn value(&self, id: usize) -> Option<ParameterValue> {
+        self.parameter(id).map(|parameter| parameter.value())
+    }
+
+    /// Sets the value of the parameter with the given id
+    ///
+    /// Returns `false` if the module doesn't have a parameter with the id.
+    fn set_value(&mut self, id: usize, value: impl Into<ParameterValue>) -> bool {
+        match self.parameter_mut(id) {
+            Some(parameter) => {
+                parameter.set_user_value(value.into().as_f32());
+                true
+            }
+            None => false,
+        }
+    }
+}
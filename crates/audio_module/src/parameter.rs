@@ -1,22 +1,135 @@
-use std::sync::Arc;
+use std::{ops::RangeInclusive, sync::Arc};
 
 use crate::{
-    BoolStringConverter,
+    BoolStringConverter, EnumStringConverter, IntStringConverter, LinearValueConverter, Smoothing,
     string_converter::{DefaultStringConverter, StringConverter},
     value_converter::{DefaultValueConverter, ValueConverter},
 };
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ValueType {
     Float,
     Bool,
+    Int,
+    Enum,
+}
+
+/// A parameter's user-facing value
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParameterValue {
+    Float(f32),
+    Bool(bool),
+    Int(i32),
+    /// The index of the selected variant
+    Enum(usize),
+}
+
+impl ParameterValue {
+    /// The value as it's sent to the processor, with bools as `0.0` or `1.0`
+    pub fn as_f32(&self) -> f32 {
+        match self {
+            Self::Float(value) => *value,
+            Self::Bool(true) => 1.0,
+            Self::Bool(false) => 0.0,
+            Self::Int(value) => *value as f32,
+            Self::Enum(index) => *index as f32,
+        }
+    }
+}
+
+impl From<f32> for ParameterValue {
+    fn from(value: f32) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<bool> for ParameterValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i32> for ParameterValue {
+    fn from(value: i32) -> Self {
+        Self::Int(value)
+    }
 }
 
 pub trait Parameter {
     fn name(&self) -> Arc<String>;
     fn default_user_value(&self) -> f32;
 
+    /// The name of the group that the parameter belongs to, for UIs that group related parameters
+    fn group(&self) -> Option<Arc<String>> {
+        None
+    }
+
+    /// True if the parameter should be left out of generated UIs and saved state
+    ///
+    /// Hidden parameters are still addressed by their id in `ToProcessor` messages, e.g. for
+    /// parameters that are controlled by the app rather than by the user.
+    fn is_hidden(&self) -> bool {
+        false
+    }
+
+    /// The parameter's index, used to address the parameter in `ToProcessor` messages
     fn id(&self) -> usize;
 
+    /// Changes the parameter's id, e.g. when the module is hosted in a `ModuleChain`
+    fn set_id(&mut self, id: usize);
+
+    /// The parameter's key, used to identify the parameter in saved state and presets
+    ///
+    /// Unlike the parameter's id, the key doesn't change when parameters are reordered or added.
+    /// Parameters without an explicit key (set with `with_key`) use a key
+    /// that's derived from their name, which changes if the parameter is renamed, so only explicit
+    /// keys should be relied on to be stable.
+    ///
+    /// **Warning:** renaming a parameter without an explicit key will cause its value to be lost
+    /// when restoring a previously saved `ModuleState`.
+    fn key(&self) -> Arc<String>;
+
+    /// Changes the parameter's key, e.g. when the module is hosted in a `ModuleChain`
+    fn set_key(&mut self, key: &str);
+
+    /// The parameter's current user-facing value
+    ///
+    /// Bools are represented as `0.0` or `1.0`, and enums by the index of the selected variant.
+    fn user_value(&self) -> f32;
+
+    /// Sets the parameter's user-facing value
+    ///
+    /// Any non-zero value enables bools, and values for stepped parameters are rounded to the
+    /// nearest step.
+    fn set_user_value(&mut self, value: f32);
+
+    /// The parameter's current value, typed according to its [ValueType]
+    fn value(&self) -> ParameterValue {
+        match self.value_type() {
+            ValueType::Float => ParameterValue::Float(self.user_value()),
+            ValueType::Bool => ParameterValue::Bool(self.user_value() != 0.0),
+            ValueType::Int => ParameterValue::Int(self.user_value().round() as i32),
+            ValueType::Enum => ParameterValue::Enum(self.user_value().round() as usize),
+        }
+    }
+
+    /// The distance between the parameter's values, for parameters that only take discrete values
+    fn step(&self) -> Option<f32> {
+        None
+    }
+
+    /// How the processor should smooth changes to the parameter's value, see `SmoothedValue`
+    fn smoothing(&self) -> Smoothing {
+        Smoothing::None
+    }
+
+    /// The labels of the parameter's variants, for parameters that choose between variants
+    ///
+    /// The selected variant's index is the parameter's user value.
+    fn variant_labels(&self) -> Option<Arc<Vec<String>>> {
+        None
+    }
+
     fn value_type(&self) -> ValueType;
     fn value_converter(&self) -> Arc<dyn ValueConverter> {
         Arc::new(DefaultValueConverter {})
@@ -29,7 +142,10 @@ pub trait Parameter {
 pub struct BoolParameter {
     pub value: bool,
     id: usize,
+    key: Arc<String>,
     name: Arc<String>,
+    group: Option<Arc<String>>,
+    hidden: bool,
     default_user_value: bool,
     string_converter: Arc<dyn StringConverter>,
 }
@@ -40,10 +156,31 @@ impl BoolParameter {
             default_user_value: default_value,
             value: default_value,
             id,
+            key: Arc::new(default_key(name)),
             name: Arc::new(name.to_string()),
+            group: None,
+            hidden: false,
             string_converter: Arc::new(BoolStringConverter),
         }
     }
+
+    /// Sets the parameter's key, see [Parameter::key]
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.key = Arc::new(key.to_string());
+        self
+    }
+
+    /// Places the parameter in a group, see [Parameter::group]
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = Some(Arc::new(group.to_string()));
+        self
+    }
+
+    /// Hides the parameter from generated UIs and saved state, see [Parameter::is_hidden]
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
 }
 
 impl Parameter for BoolParameter {
@@ -51,10 +188,38 @@ impl Parameter for BoolParameter {
         self.id
     }
 
+    fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
+
+    fn key(&self) -> Arc<String> {
+        self.key.clone()
+    }
+
+    fn set_key(&mut self, key: &str) {
+        self.key = Arc::new(key.to_string());
+    }
+
+    fn user_value(&self) -> f32 {
+        if self.value { 1.0 } else { 0.0 }
+    }
+
+    fn set_user_value(&mut self, value: f32) {
+        self.value = value != 0.0;
+    }
+
     fn name(&self) -> Arc<String> {
         self.name.clone()
     }
 
+    fn group(&self) -> Option<Arc<String>> {
+        self.group.clone()
+    }
+
+    fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
     fn default_user_value(&self) -> f32 {
         if self.default_user_value { 1.0 } else { 0.0 }
     }
@@ -72,7 +237,10 @@ impl PartialEq for BoolParameter {
     fn eq(&self, other: &Self) -> bool {
         self.value == other.value
             && self.id == other.id
+            && self.key == other.key
             && self.name == other.name
+            && self.group == other.group
+            && self.hidden == other.hidden
             && self.default_user_value == other.default_user_value
             && Arc::ptr_eq(&self.string_converter, &other.string_converter)
     }
@@ -80,18 +248,46 @@ impl PartialEq for BoolParameter {
 
 pub struct FloatParameterBuilder {
     id: usize,
+    key: Option<String>,
     name: String,
+    group: Option<String>,
+    hidden: bool,
     default_user_value: f32,
+    smoothing: Smoothing,
     value_converter: Option<Arc<dyn ValueConverter>>,
     string_converter: Option<Arc<dyn StringConverter>>,
 }
 
 impl FloatParameterBuilder {
+    /// Sets the parameter's key, see [Parameter::key]
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    /// Places the parameter in a group, see [Parameter::group]
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+
+    /// Hides the parameter from generated UIs and saved state, see [Parameter::is_hidden]
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
     pub fn default_user_value(mut self, default: f32) -> Self {
         self.default_user_value = default;
         self
     }
 
+    /// Sets how the processor should smooth changes to the parameter, see [Parameter::smoothing]
+    pub fn smoothing(mut self, smoothing: Smoothing) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
     pub fn with_value_converter(mut self, converter: impl ValueConverter + 'static) -> Self {
         self.value_converter = Some(Arc::new(converter));
         self
@@ -106,8 +302,12 @@ impl FloatParameterBuilder {
         FloatParameter {
             value: self.default_user_value,
             id: self.id,
+            key: Arc::new(self.key.unwrap_or_else(|| default_key(&self.name))),
             name: Arc::new(self.name),
+            group: self.group.map(Arc::new),
+            hidden: self.hidden,
             default_user_value: self.default_user_value,
+            smoothing: self.smoothing,
             value_converter: self
                 .value_converter
                 .unwrap_or_else(|| Arc::new(DefaultValueConverter::default())),
@@ -122,8 +322,12 @@ impl FloatParameterBuilder {
 pub struct FloatParameter {
     pub value: f32,
     id: usize,
+    key: Arc<String>,
     name: Arc<String>,
+    group: Option<Arc<String>>,
+    hidden: bool,
     default_user_value: f32,
+    smoothing: Smoothing,
     value_converter: Arc<dyn ValueConverter>,
     string_converter: Arc<dyn StringConverter>,
 }
@@ -132,8 +336,12 @@ impl FloatParameter {
     pub fn builder(name: &str, id: usize) -> FloatParameterBuilder {
         FloatParameterBuilder {
             id,
+            key: None,
             name: name.to_string(),
+            group: None,
+            hidden: false,
             default_user_value: 0.0,
+            smoothing: Smoothing::None,
             value_converter: None,
             string_converter: None,
         }
@@ -149,14 +357,46 @@ impl Parameter for FloatParameter {
         self.id
     }
 
+    fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
+
+    fn key(&self) -> Arc<String> {
+        self.key.clone()
+    }
+
+    fn set_key(&mut self, key: &str) {
+        self.key = Arc::new(key.to_string());
+    }
+
+    fn user_value(&self) -> f32 {
+        self.value
+    }
+
+    fn set_user_value(&mut self, value: f32) {
+        self.value = value;
+    }
+
     fn name(&self) -> Arc<String> {
         self.name.clone()
     }
 
+    fn group(&self) -> Option<Arc<String>> {
+        self.group.clone()
+    }
+
+    fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
     fn default_user_value(&self) -> f32 {
         self.default_user_value
     }
 
+    fn smoothing(&self) -> Smoothing {
+        self.smoothing
+    }
+
     fn value_converter(&self) -> Arc<dyn ValueConverter> {
         self.value_converter.clone()
     }
@@ -170,9 +410,476 @@ impl PartialEq for FloatParameter {
     fn eq(&self, other: &Self) -> bool {
         self.value == other.value
             && self.id == other.id
+            && self.key == other.key
+            && self.name == other.name
+            && self.group == other.group
+            && self.hidden == other.hidden
+            && self.default_user_value == other.default_user_value
+            && self.smoothing == other.smoothing
+            && Arc::ptr_eq(&self.value_converter, &other.value_converter)
+            && Arc::ptr_eq(&self.string_converter, &other.string_converter)
+    }
+}
+
+pub struct IntParameterBuilder {
+    id: usize,
+    key: Option<String>,
+    name: String,
+    group: Option<String>,
+    hidden: bool,
+    default_user_value: i32,
+    range: RangeInclusive<i32>,
+    step: i32,
+    string_converter: Option<Arc<dyn StringConverter>>,
+}
+
+impl IntParameterBuilder {
+    /// Sets the parameter's key, see [Parameter::key]
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    /// Places the parameter in a group, see [Parameter::group]
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+
+    /// Hides the parameter from generated UIs and saved state, see [Parameter::is_hidden]
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    pub fn default_user_value(mut self, default: i32) -> Self {
+        self.default_user_value = default;
+        self
+    }
+
+    /// Sets the distance between the parameter's values, starting from the minimum value
+    pub fn step(mut self, step: i32) -> Self {
+        self.step = step.max(1);
+        self
+    }
+
+    pub fn string_converter(mut self, converter: impl StringConverter + 'static) -> Self {
+        self.string_converter = Some(Arc::new(converter));
+        self
+    }
+
+    pub fn build(self) -> IntParameter {
+        // Inverted ranges are treated as if they were in ascending order
+        let (start, end) = (*self.range.start(), *self.range.end());
+        let (min, max) = (start.min(end), start.max(end));
+        let default_user_value = self.default_user_value.clamp(min, max);
+
+        IntParameter {
+            value: default_user_value,
+            id: self.id,
+            key: Arc::new(self.key.unwrap_or_else(|| default_key(&self.name))),
+            name: Arc::new(self.name),
+            group: self.group.map(Arc::new),
+            hidden: self.hidden,
+            default_user_value,
+            min,
+            max,
+            step: self.step,
+            value_converter: Arc::new(LinearValueConverter::new(min as f32, max as f32)),
+            string_converter: self
+                .string_converter
+                .unwrap_or_else(|| Arc::new(IntStringConverter::default())),
+        }
+    }
+}
+
+/// A parameter that takes whole number values within a range, e.g. a voice count
+#[derive(Clone)]
+pub struct IntParameter {
+    pub value: i32,
+    id: usize,
+    key: Arc<String>,
+    name: Arc<String>,
+    group: Option<Arc<String>>,
+    hidden: bool,
+    default_user_value: i32,
+    min: i32,
+    max: i32,
+    step: i32,
+    value_converter: Arc<dyn ValueConverter>,
+    string_converter: Arc<dyn StringConverter>,
+}
+
+impl IntParameter {
+    pub fn builder(name: &str, id: usize, range: RangeInclusive<i32>) -> IntParameterBuilder {
+        IntParameterBuilder {
+            id,
+            key: None,
+            name: name.to_string(),
+            group: None,
+            hidden: false,
+            default_user_value: *range.start(),
+            range,
+            step: 1,
+            string_converter: None,
+        }
+    }
+
+    pub fn min(&self) -> i32 {
+        self.min
+    }
+
+    pub fn max(&self) -> i32 {
+        self.max
+    }
+}
+
+impl Parameter for IntParameter {
+    fn value_type(&self) -> ValueType {
+        ValueType::Int
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
+
+    fn key(&self) -> Arc<String> {
+        self.key.clone()
+    }
+
+    fn set_key(&mut self, key: &str) {
+        self.key = Arc::new(key.to_string());
+    }
+
+    fn user_value(&self) -> f32 {
+        self.value as f32
+    }
+
+    fn set_user_value(&mut self, value: f32) {
+        // The arithmetic is widened so that ranges spanning most of i32 don't overflow
+        let (min, max, step) = (self.min as i64, self.max as i64, self.step as i64);
+        let max_steps = (max - min) / step;
+        let steps = ((value as f64 - min as f64) / step as f64).round() as i64;
+        self.value = (min + steps.clamp(0, max_steps) * step) as i32;
+    }
+
+    fn step(&self) -> Option<f32> {
+        Some(self.step as f32)
+    }
+
+    fn name(&self) -> Arc<String> {
+        self.name.clone()
+    }
+
+    fn group(&self) -> Option<Arc<String>> {
+        self.group.clone()
+    }
+
+    fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    fn default_user_value(&self) -> f32 {
+        self.default_user_value as f32
+    }
+
+    fn value_converter(&self) -> Arc<dyn ValueConverter> {
+        self.value_converter.clone()
+    }
+
+    fn string_converter(&self) -> Arc<dyn StringConverter> {
+        self.string_converter.clone()
+    }
+}
+
+impl PartialEq for IntParameter {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+            && self.id == other.id
+            && self.key == other.key
             && self.name == other.name
+            && self.group == other.group
+            && self.hidden == other.hidden
             && self.default_user_value == other.default_user_value
+            && self.min == other.min
+            && self.max == other.max
+            && self.step == other.step
             && Arc::ptr_eq(&self.value_converter, &other.value_converter)
             && Arc::ptr_eq(&self.string_converter, &other.string_converter)
     }
 }
+
+/// A parameter that chooses one of a set of labelled variants, e.g. a filter type
+///
+/// The parameter's value is the index of the selected variant.
+#[derive(Clone)]
+pub struct EnumParameter {
+    pub value: usize,
+    id: usize,
+    key: Arc<String>,
+    name: Arc<String>,
+    group: Option<Arc<String>>,
+    hidden: bool,
+    default_user_value: usize,
+    labels: Arc<Vec<String>>,
+    value_converter: Arc<dyn ValueConverter>,
+    string_converter: Arc<dyn StringConverter>,
+}
+
+impl EnumParameter {
+    /// Creates the parameter, with the default value clamped to the index of the last label
+    ///
+    /// # Panics
+    ///
+    /// Panics if `labels` is empty.
+    pub fn new(name: &str, id: usize, labels: &[&str], default_value: usize) -> Self {
+        assert!(
+            !labels.is_empty(),
+            "enum parameters need at least one label (parameter: {name})"
+        );
+
+        let labels: Arc<Vec<String>> =
+            Arc::new(labels.iter().map(|label| label.to_string()).collect());
+        let last = labels.len() - 1;
+        let default_value = default_value.min(last);
+
+        Self {
+            value: default_value,
+            id,
+            key: Arc::new(default_key(name)),
+            name: Arc::new(name.to_string()),
+            group: None,
+            hidden: false,
+            default_user_value: default_value,
+            value_converter: Arc::new(LinearValueConverter::new(0.0, last as f32)),
+            string_converter: Arc::new(EnumStringConverter::new(labels.clone())),
+            labels,
+        }
+    }
+
+    /// Sets the parameter's key, see [Parameter::key]
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.key = Arc::new(key.to_string());
+        self
+    }
+
+    /// Places the parameter in a group, see [Parameter::group]
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = Some(Arc::new(group.to_string()));
+        self
+    }
+
+    /// Hides the parameter from generated UIs and saved state, see [Parameter::is_hidden]
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    /// The labels of the parameter's variants
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    /// The label of the selected variant, or the last variant if the value is out of range
+    pub fn selected_label(&self) -> &str {
+        &self.labels[self.value.min(self.labels.len() - 1)]
+    }
+}
+
+impl Parameter for EnumParameter {
+    fn value_type(&self) -> ValueType {
+        ValueType::Enum
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
+
+    fn key(&self) -> Arc<String> {
+        self.key.clone()
+    }
+
+    fn set_key(&mut self, key: &str) {
+        self.key = Arc::new(key.to_string());
+    }
+
+    fn user_value(&self) -> f32 {
+        self.value as f32
+    }
+
+    fn set_user_value(&mut self, value: f32) {
+        self.value = (value.round().max(0.0) as usize).min(self.labels.len() - 1);
+    }
+
+    fn step(&self) -> Option<f32> {
+        Some(1.0)
+    }
+
+    fn variant_labels(&self) -> Option<Arc<Vec<String>>> {
+        Some(self.labels.clone())
+    }
+
+    fn name(&self) -> Arc<String> {
+        self.name.clone()
+    }
+
+    fn group(&self) -> Option<Arc<String>> {
+        self.group.clone()
+    }
+
+    fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    fn default_user_value(&self) -> f32 {
+        self.default_user_value as f32
+    }
+
+    fn value_converter(&self) -> Arc<dyn ValueConverter> {
+        self.value_converter.clone()
+    }
+
+    fn string_converter(&self) -> Arc<dyn StringConverter> {
+        self.string_converter.clone()
+    }
+}
+
+impl PartialEq for EnumParameter {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+            && self.id == other.id
+            && self.key == other.key
+            && self.name == other.name
+            && self.group == other.group
+            && self.hidden == other.hidden
+            && self.default_user_value == other.default_user_value
+            && self.labels == other.labels
+            && Arc::ptr_eq(&self.value_converter, &other.value_converter)
+            && Arc::ptr_eq(&self.string_converter, &other.string_converter)
+    }
+}
+
+/// The key that's used for parameters without an explicit key, e.g. `room_size` for "Room Size"
+///
+/// Derived keys aren't stable across renames, see [Parameter::key].
+fn default_key(name: &str) -> String {
+    name.split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn int_values_snap_to_steps() {
+        let mut parameter = IntParameter::builder("Voices", 0, 1..=16).step(3).build();
+
+        // Steps start from the minimum: 1, 4, 7, 10, 13, 16
+        parameter.set_user_value(5.0);
+        assert_eq!(parameter.value, 4);
+        parameter.set_user_value(5.6);
+        assert_eq!(parameter.value, 7);
+        parameter.set_user_value(-10.0);
+        assert_eq!(parameter.value, 1);
+        parameter.set_user_value(100.0);
+        assert_eq!(parameter.value, 16);
+    }
+
+    #[test]
+    fn int_values_stay_on_steps_below_max() {
+        // The maximum isn't on a step, so the largest value is the last step
+        let mut parameter = IntParameter::builder("Size", 0, 0..=10).step(4).build();
+
+        parameter.set_user_value(10.0);
+        assert_eq!(parameter.value, 8);
+    }
+
+    #[test]
+    fn int_values_use_the_full_i32_range() {
+        let mut parameter = IntParameter::builder("Offset", 0, i32::MIN..=i32::MAX).build();
+
+        parameter.set_user_value(f32::MAX);
+        assert_eq!(parameter.value, i32::MAX);
+        parameter.set_user_value(f32::MIN);
+        assert_eq!(parameter.value, i32::MIN);
+    }
+
+    #[test]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn inverted_int_ranges_are_normalized() {
+        let mut parameter = IntParameter::builder("Octave", 0, 3..=-3)
+            .default_user_value(10)
+            .build();
+
+        assert_eq!(parameter.min(), -3);
+        assert_eq!(parameter.max(), 3);
+        // The default is clamped to the normalized range
+        assert_eq!(parameter.value, 3);
+        assert_eq!(parameter.value_converter().min(), -3.0);
+        assert_eq!(parameter.value_converter().max(), 3.0);
+
+        parameter.set_user_value(-2.4);
+        assert_eq!(parameter.value, -2);
+    }
+
+    #[test]
+    fn single_value_int_ranges_are_not_padded() {
+        let mut parameter = IntParameter::builder("Fixed", 0, 5..=5).build();
+        let value_converter = parameter.value_converter();
+
+        assert_eq!(value_converter.min(), 5.0);
+        assert_eq!(value_converter.max(), 5.0);
+        assert_eq!(value_converter.user_to_linear(5.0), 0.0);
+        assert_eq!(value_converter.linear_to_user(1.0), 5.0);
+
+        parameter.set_user_value(6.0);
+        assert_eq!(parameter.value, 5);
+    }
+
+    #[test]
+    fn enum_values_are_rounded_and_clamped() {
+        let mut parameter = EnumParameter::new("Mode", 0, &["A", "B", "C"], 1);
+
+        parameter.set_user_value(1.6);
+        assert_eq!(parameter.value, 2);
+        parameter.set_user_value(-1.0);
+        assert_eq!(parameter.value, 0);
+        parameter.set_user_value(10.0);
+        assert_eq!(parameter.value, 2);
+        assert_eq!(parameter.selected_label(), "C");
+    }
+
+    #[test]
+    fn single_label_enums_have_a_single_variant() {
+        let mut parameter = EnumParameter::new("Mode", 0, &["Only"], 3);
+        let value_converter = parameter.value_converter();
+
+        assert_eq!(parameter.value, 0);
+        assert_eq!(value_converter.min(), 0.0);
+        assert_eq!(value_converter.max(), 0.0);
+        assert_eq!(
+            parameter.variant_labels().as_deref(),
+            Some(&vec!["Only".to_string()])
+        );
+
+        parameter.set_user_value(1.0);
+        assert_eq!(parameter.value, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn enums_without_labels_panic() {
+        EnumParameter::new("Mode", 0, &[], 0);
+    }
+}
@@ -0,0 +1,236 @@
+/// How a [SmoothedValue] moves towards a new target value
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Smoothing {
+    /// New values are applied immediately
+    #[default]
+    None,
+    /// The value ramps linearly to the target over the given time in seconds
+    Linear(f32),
+    /// The value approaches the target exponentially, getting within 1% of the target in the
+    /// given time in seconds
+    Exponential(f32),
+}
+
+/// The remaining distance to the target at which exponential smoothing snaps to the target
+const EXPONENTIAL_THRESHOLD: f32 = 1.0e-5;
+
+/// A value that's smoothed towards its target one sample at a time, avoiding zipper noise
+///
+/// Smoothed values don't allocate, so they can be used in the processor, with new targets set
+/// when `ToProcessor` messages are received and [SmoothedValue::tick] called for each frame.
+#[derive(Clone, Debug)]
+pub struct SmoothedValue {
+    current: f32,
+    target: f32,
+    smoothing: Smoothing,
+    sample_rate: f32,
+    // The per-sample increment and the number of remaining steps for linear smoothing
+    increment: f32,
+    remaining_steps: usize,
+    // The per-sample coefficient for exponential smoothing
+    coefficient: f32,
+}
+
+impl SmoothedValue {
+    pub fn new(value: f32, smoothing: Smoothing, sample_rate: usize) -> Self {
+        let mut result = Self {
+            current: value,
+            target: value,
+            smoothing,
+            sample_rate: sample_rate as f32,
+            increment: 0.0,
+            remaining_steps: 0,
+            coefficient: 0.0,
+        };
+        result.set_smoothing(smoothing);
+        result
+    }
+
+    /// Changes how the value is smoothed, applying any pending target immediately
+    pub fn set_smoothing(&mut self, smoothing: Smoothing) {
+        self.smoothing = smoothing;
+        self.coefficient = match smoothing {
+            // The coefficient leaves 1% of the distance to the target after the smoothing time
+            Smoothing::Exponential(time) if time > 0.0 => {
+                0.01_f32.powf(1.0 / (time * self.sample_rate))
+            }
+            _ => 0.0,
+        };
+        self.set_immediate(self.target);
+    }
+
+    /// Sets the value that will be smoothed towards
+    pub fn set_target(&mut self, target: f32) {
+        self.target = target;
+
+        match self.smoothing {
+            Smoothing::Linear(time) => {
+                self.remaining_steps = (time * self.sample_rate).round() as usize;
+                if self.remaining_steps == 0 {
+                    self.current = target;
+                } else {
+                    self.increment = (target - self.current) / self.remaining_steps as f32;
+                }
+            }
+            Smoothing::Exponential(_) if self.coefficient > 0.0 => {}
+            _ => self.current = target,
+        }
+    }
+
+    /// Sets the value without any smoothing
+    pub fn set_immediate(&mut self, value: f32) {
+        self.current = value;
+        self.target = value;
+        self.remaining_steps = 0;
+    }
+
+    /// Advances the value by a single sample, returning the new value
+    pub fn tick(&mut self) -> f32 {
+        match self.smoothing {
+            Smoothing::Linear(_) if self.remaining_steps > 0 => {
+                self.remaining_steps -= 1;
+                self.current = if self.remaining_steps == 0 {
+                    self.target
+                } else {
+                    self.current + self.increment
+                };
+            }
+            Smoothing::Exponential(_) if self.current != self.target => {
+                let distance = self.current - self.target;
+                self.current = if distance.abs() < EXPONENTIAL_THRESHOLD {
+                    self.target
+                } else {
+                    self.target + distance * self.coefficient
+                };
+            }
+            _ => {}
+        }
+
+        self.current
+    }
+
+    /// The current value
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    /// The value that's being smoothed towards
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    /// True if the value hasn't reached its target yet
+    pub fn is_smoothing(&self) -> bool {
+        self.current != self.target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: usize = 1000;
+
+    #[test]
+    fn linear_smoothing_reaches_the_target_after_the_smoothing_time() {
+        // 10 samples at 1kHz
+        let mut value = SmoothedValue::new(0.0, Smoothing::Linear(0.01), SAMPLE_RATE);
+        value.set_target(1.0);
+
+        for step in 1..10 {
+            let current = value.tick();
+            assert!((current - step as f32 * 0.1).abs() < 1.0e-6);
+            assert!(value.is_smoothing());
+        }
+
+        assert_eq!(value.tick(), 1.0);
+        assert!(!value.is_smoothing());
+        assert_eq!(value.tick(), 1.0);
+    }
+
+    #[test]
+    fn linear_smoothing_restarts_from_the_current_value() {
+        let mut value = SmoothedValue::new(0.0, Smoothing::Linear(0.01), SAMPLE_RATE);
+        value.set_target(1.0);
+        for _ in 0..5 {
+            value.tick();
+        }
+
+        value.set_target(0.0);
+        for _ in 0..9 {
+            value.tick();
+        }
+        assert!((value.current() - 0.05).abs() < 1.0e-6);
+        assert_eq!(value.tick(), 0.0);
+    }
+
+    #[test]
+    fn exponential_smoothing_converges_on_the_target() {
+        let mut value = SmoothedValue::new(0.0, Smoothing::Exponential(0.01), SAMPLE_RATE);
+        value.set_target(1.0);
+
+        // Within 1% of the target after the smoothing time
+        for _ in 0..10 {
+            value.tick();
+        }
+        assert!((value.current() - 0.99).abs() < 1.0e-4);
+        assert!(value.is_smoothing());
+
+        // The value snaps to the target once it's close enough
+        let mut ticks = 0;
+        while value.is_smoothing() {
+            value.tick();
+            ticks += 1;
+            assert!(ticks < SAMPLE_RATE, "the value didn't reach its target");
+        }
+        assert_eq!(value.current(), 1.0);
+    }
+
+    #[test]
+    fn values_at_their_target_are_not_smoothing() {
+        for smoothing in [
+            Smoothing::None,
+            Smoothing::Linear(0.01),
+            Smoothing::Exponential(0.01),
+        ] {
+            let mut value = SmoothedValue::new(0.5, smoothing, SAMPLE_RATE);
+            assert!(!value.is_smoothing());
+
+            value.set_target(0.5);
+            assert!(!value.is_smoothing());
+            assert_eq!(value.tick(), 0.5);
+        }
+    }
+
+    #[test]
+    fn values_without_smoothing_change_immediately() {
+        for smoothing in [
+            Smoothing::None,
+            Smoothing::Linear(0.0),
+            Smoothing::Exponential(0.0),
+        ] {
+            let mut value = SmoothedValue::new(0.0, smoothing, SAMPLE_RATE);
+            value.set_target(1.0);
+            assert_eq!(value.current(), 1.0);
+            assert!(!value.is_smoothing());
+        }
+    }
+
+    #[test]
+    fn set_smoothing_applies_the_pending_target() {
+        let mut value = SmoothedValue::new(0.0, Smoothing::Linear(0.01), SAMPLE_RATE);
+        value.set_target(1.0);
+        value.tick();
+        assert!(value.is_smoothing());
+
+        value.set_smoothing(Smoothing::Exponential(0.01));
+        assert_eq!(value.current(), 1.0);
+        assert_eq!(value.target(), 1.0);
+        assert!(!value.is_smoothing());
+
+        // The new smoothing is used for the next target
+        value.set_target(0.0);
+        assert!(value.tick() > 0.0);
+        assert!(value.is_smoothing());
+    }
+}
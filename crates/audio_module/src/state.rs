@@ -0,0 +1,266 @@
+use std::{borrow::Cow, cmp::Ordering, collections::BTreeMap};
+
+use crate::{AudioModule, Parameters, ToProcessor};
+use thiserror::Error;
+
+/// A snapshot of a module's parameter values, e.g. for saving presets or the state of a session
+///
+/// Values are stored by their parameters' keys rather than their ids, so that state can be
+/// restored after parameters have been reordered or added. Keys that are derived from parameter
+/// names change when parameters are renamed, so modules that save state should give each of their
+/// parameters an explicit key, see [Parameter::key](crate::Parameter::key).
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleState {
+    /// The module's `AudioModule::VERSION` when the state was saved
+    pub version: u32,
+    /// The user-facing value of each parameter, by key
+    pub values: BTreeMap<String, f32>,
+}
+
+impl ModuleState {
+    /// Saves the current values of a module's parameters
+    ///
+    /// Hidden parameters aren't saved, see `Parameter::is_hidden`.
+    pub fn save<M: AudioModule>(parameters: &M::Parameters) -> Self {
+        Self {
+            version: M::VERSION,
+            values: parameters
+                .parameters()
+                .into_iter()
+                .filter(|parameter| !parameter.is_hidden())
+                .map(|parameter| (parameter.key().to_string(), parameter.user_value()))
+                .collect(),
+        }
+    }
+
+    /// Applies the saved values to a module's parameters
+    ///
+    /// State that was saved by an earlier version of the module is first passed to
+    /// [AudioModule::migrate_state], and state from a newer version is rejected.
+    ///
+    /// Values for unknown keys and hidden parameters are ignored, and parameters without a saved
+    /// value are left unchanged. A `ToProcessor::SetParameter` message is returned for each parameter whose value
+    /// was changed, for passing on to the module's processor.
+    pub fn apply<M: AudioModule>(
+        &self,
+        parameters: &mut M::Parameters,
+    ) -> Result<Vec<ToProcessor>, StateError> {
+        let state = match self.version.cmp(&M::VERSION) {
+            Ordering::Equal => Cow::Borrowed(self),
+            Ordering::Less => {
+                let mut state = self.clone();
+                M::migrate_state(&mut state);
+                state.version = M::VERSION;
+                Cow::Owned(state)
+            }
+            Ordering::Greater => {
+                return Err(StateError::UnsupportedVersion {
+                    version: self.version,
+                    module_version: M::VERSION,
+                });
+            }
+        };
+
+        Ok(parameters
+            .parameters_mut()
+            .into_iter()
+            .filter(|parameter| !parameter.is_hidden())
+            .filter_map(|parameter| {
+                let value = *state.values.get(parameter.key().as_str())?;
+                let previous_value = parameter.user_value();
+                parameter.set_user_value(value);
+
+                (parameter.user_value() != previous_value)
+                    .then(|| ToProcessor::SetParameter(parameter.id(), parameter.user_value()))
+            })
+            .collect())
+    }
+}
+
+/// Errors that can occur when applying a [ModuleState]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    #[error(
+        "the state was saved by version {version} of the module, \
+         only versions up to {module_version} are supported"
+    )]
+    UnsupportedVersion { version: u32, module_version: u32 },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BoolParameter, FloatParameter, Parameter};
+
+    struct TestModule;
+
+    struct TestParameters {
+        gain: FloatParameter,
+        mute: BoolParameter,
+        meter: BoolParameter,
+    }
+
+    impl Default for TestParameters {
+        fn default() -> Self {
+            Self {
+                gain: FloatParameter::builder("Gain", 0)
+                    .with_key("gain")
+                    .default_user_value(0.5)
+                    .build(),
+                mute: BoolParameter::new("Mute", 1, false).with_key("mute"),
+                meter: BoolParameter::new("Meter", 2, false)
+                    .with_key("meter")
+                    .hidden(),
+            }
+        }
+    }
+
+    impl Parameters for TestParameters {
+        fn parameters(&self) -> Vec<&dyn Parameter> {
+            vec![&self.gain, &self.mute, &self.meter]
+        }
+
+        fn parameters_mut(&mut self) -> Vec<&mut dyn Parameter> {
+            vec![&mut self.gain, &mut self.mute, &mut self.meter]
+        }
+    }
+
+    struct TestProcessor;
+
+    impl crate::AudioProcessor for TestProcessor {
+        type OutputMessage = ();
+
+        fn process_buffer<To, From>(
+            &mut self,
+            _buffer: &mut [f32],
+            _channels: usize,
+            _to_processor: &To,
+            _from_processor: &From,
+        ) where
+            To: crate::PopMessage<ToProcessor>,
+            From: crate::PushMessage<Self::OutputMessage>,
+        {
+        }
+    }
+
+    impl AudioModule for TestModule {
+        const NAME: &'static str = "Test";
+        const VERSION: u32 = 2;
+
+        type Parameters = TestParameters;
+        type Processor = TestProcessor;
+
+        fn create_processor(_sample_rate: usize) -> Self::Processor {
+            TestProcessor
+        }
+
+        // Version 1 saved the gain as `level`, in the range 0..=2
+        fn migrate_state(state: &mut ModuleState) {
+            if state.version < 2
+                && let Some(level) = state.values.remove("level")
+            {
+                state.values.insert("gain".to_string(), level / 2.0);
+            }
+        }
+    }
+
+    fn state(version: u32, values: &[(&str, f32)]) -> ModuleState {
+        ModuleState {
+            version,
+            values: values
+                .iter()
+                .map(|(key, value)| (key.to_string(), *value))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn saved_state_round_trips() {
+        let mut parameters = TestParameters::default();
+        parameters.gain.set_user_value(0.8);
+        parameters.mute.set_user_value(1.0);
+
+        let saved = ModuleState::save::<TestModule>(&parameters);
+        assert_eq!(saved, state(2, &[("gain", 0.8), ("mute", 1.0)]));
+
+        let mut restored = TestParameters::default();
+        let messages = saved.apply::<TestModule>(&mut restored).unwrap();
+
+        assert_eq!(restored.gain.user_value(), 0.8);
+        assert_eq!(restored.mute.user_value(), 1.0);
+        assert!(matches!(
+            messages.as_slice(),
+            [
+                ToProcessor::SetParameter(0, 0.8),
+                ToProcessor::SetParameter(1, 1.0)
+            ]
+        ));
+
+        // Applying the same state again doesn't change anything
+        let messages = saved.apply::<TestModule>(&mut restored).unwrap();
+        assert!(messages.is_empty());
+    }
+
+    #[test]
+    fn unknown_keys_are_ignored() {
+        let mut parameters = TestParameters::default();
+        let messages = state(2, &[("mute", 1.0), ("removed", 0.25)])
+            .apply::<TestModule>(&mut parameters)
+            .unwrap();
+
+        // The gain doesn't have a saved value, so it keeps its default
+        assert_eq!(parameters.gain.user_value(), 0.5);
+        assert_eq!(parameters.mute.user_value(), 1.0);
+        assert!(matches!(
+            messages.as_slice(),
+            [ToProcessor::SetParameter(1, 1.0)]
+        ));
+    }
+
+    #[test]
+    fn hidden_parameters_are_not_saved_or_applied() {
+        let mut parameters = TestParameters::default();
+        parameters.meter.set_user_value(1.0);
+
+        let saved = ModuleState::save::<TestModule>(&parameters);
+        assert!(!saved.values.contains_key("meter"));
+
+        let mut restored = TestParameters::default();
+        let messages = state(2, &[("meter", 1.0)])
+            .apply::<TestModule>(&mut restored)
+            .unwrap();
+        assert_eq!(restored.meter.user_value(), 0.0);
+        assert!(messages.is_empty());
+    }
+
+    #[test]
+    fn state_from_a_newer_version_is_rejected() {
+        let mut parameters = TestParameters::default();
+        let result = state(3, &[("gain", 0.1)]).apply::<TestModule>(&mut parameters);
+
+        assert_eq!(
+            result.unwrap_err(),
+            StateError::UnsupportedVersion {
+                version: 3,
+                module_version: 2
+            }
+        );
+        assert_eq!(parameters.gain.user_value(), 0.5);
+    }
+
+    #[test]
+    fn state_from_an_older_version_is_migrated() {
+        let mut parameters = TestParameters::default();
+        let old_state = state(1, &[("level", 1.5), ("mute", 1.0)]);
+        let messages = old_state.apply::<TestModule>(&mut parameters).unwrap();
+
+        assert_eq!(parameters.gain.user_value(), 0.75);
+        assert_eq!(parameters.mute.user_value(), 1.0);
+        assert_eq!(messages.len(), 2);
+
+        // The saved state itself is left unchanged
+        assert_eq!(old_state.version, 1);
+        assert!(old_state.values.contains_key("level"));
+    }
+}
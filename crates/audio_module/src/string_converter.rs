@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 pub trait StringConverter: Send + Sync {
     fn to_string(&self, value: f32) -> String;
     fn to_string_with_unit(&self, value: f32) -> String {
@@ -77,3 +79,63 @@ impl StringConverter for BoolStringConverter {
         }
     }
 }
+
+#[derive(Default, Clone)]
+pub struct IntStringConverter {
+    unit: Option<String>,
+}
+
+impl IntStringConverter {
+    pub fn new(unit: impl Into<String>) -> Self {
+        Self {
+            unit: Some(unit.into()),
+        }
+    }
+}
+
+impl StringConverter for IntStringConverter {
+    fn to_string(&self, value: f32) -> String {
+        format!("{}", value.round() as i32)
+    }
+
+    fn to_string_with_unit(&self, value: f32) -> String {
+        match &self.unit {
+            Some(unit) => format!("{} {unit}", value.round() as i32),
+            None => self.to_string(value),
+        }
+    }
+
+    fn to_f32(&self, s: &str) -> Option<f32> {
+        s.trim().parse::<i32>().ok().map(|n| n as f32)
+    }
+
+    fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+}
+
+/// Converts between an enum parameter's variant indices and their labels
+#[derive(Clone)]
+pub struct EnumStringConverter {
+    labels: Arc<Vec<String>>,
+}
+
+impl EnumStringConverter {
+    pub fn new(labels: Arc<Vec<String>>) -> Self {
+        Self { labels }
+    }
+}
+
+impl StringConverter for EnumStringConverter {
+    fn to_string(&self, value: f32) -> String {
+        let index = (value.round().max(0.0) as usize).min(self.labels.len().saturating_sub(1));
+        self.labels.get(index).cloned().unwrap_or_default()
+    }
+
+    fn to_f32(&self, s: &str) -> Option<f32> {
+        self.labels
+            .iter()
+            .position(|label| label == s)
+            .map(|index| index as f32)
+    }
+}
@@ -57,6 +57,11 @@ impl ValueConverter for LinearValueConverter {
     }
 
     fn user_to_linear(&self, value: f32) -> f32 {
+        // Ranges with a single value, e.g. an enum with one variant, are always at the minimum
+        if self.user_value_range == 0.0 {
+            return 0.0;
+        }
+
         (value - self.min_user_value) / self.user_value_range
     }
 
@@ -0,0 +1,286 @@
+use super::{
+    Error, FromProcessorSender, OutputMap, ProcessorConfig, ProcessorRunner, ProcessorTaps, Result,
+    SharedStreamState, StreamChannel, ToProcessorReceiver,
+};
+use audio_module::AudioProcessor;
+use cpal::{
+    BufferSize, Device, OutputCallbackInfo, SampleRate, Stream, StreamConfig as DeviceStreamConfig,
+    traits::DeviceTrait,
+};
+use crossbeam_channel::{Receiver, Sender, unbounded};
+use fixed_resample::{ReadStatus, ResamplingCons};
+use log::{debug, error, warn};
+use std::{sync::Arc, time::Duration};
+
+/// How long to wait for a previous output stream to return the processor
+const PROCESSOR_RETURN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Owns the processor while no output stream is running in [ProcessingMode::Callback] mode
+///
+/// [ProcessingMode::Callback]: crate::ProcessingMode::Callback
+pub(crate) struct CallbackProcessorState<P: AudioProcessor> {
+    runner: Option<ProcessorRunner<P>>,
+    create_processor: fn(usize) -> P,
+    sample_rate: usize,
+    channels: usize,
+    to_processor: ToProcessorReceiver,
+    from_processor: FromProcessorSender<P>,
+    runner_return_sender: Sender<ProcessorRunner<P>>,
+    runner_return_receiver: Receiver<ProcessorRunner<P>>,
+    return_timeout: Duration,
+    // Used to create new taps if the processor isn't returned
+    detached_taps: ProcessorTaps,
+    error_sender: Sender<Error>,
+    shared: Arc<SharedStreamState>,
+}
+
+impl<P: AudioProcessor> CallbackProcessorState<P> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        create_processor: fn(usize) -> P,
+        sample_rate: usize,
+        channels: usize,
+        to_processor: ToProcessorReceiver,
+        from_processor: FromProcessorSender<P>,
+        taps: ProcessorTaps,
+        error_sender: Sender<Error>,
+        shared: Arc<SharedStreamState>,
+    ) -> Self {
+        let (runner_return_sender, runner_return_receiver) = unbounded();
+
+        let detached_taps = taps.detached();
+        let runner = ProcessorRunner::new(
+            create_processor(sample_rate),
+            taps,
+            channels,
+            sample_rate,
+            to_processor.clone(),
+            from_processor.clone(),
+            error_sender.clone(),
+            shared.clone(),
+        );
+
+        Self {
+            runner: Some(runner),
+            create_processor,
+            sample_rate,
+            channels,
+            to_processor,
+            from_processor,
+            runner_return_sender,
+            runner_return_receiver,
+            return_timeout: PROCESSOR_RETURN_TIMEOUT,
+            detached_taps,
+            error_sender,
+            shared,
+        }
+    }
+
+    /// Sets how long [CallbackProcessorState::take_runner] waits for the processor to be returned
+    pub fn with_return_timeout(mut self, timeout: Duration) -> Self {
+        self.return_timeout = timeout;
+        self
+    }
+
+    /// Takes the processor's runner so that it can be moved into a new output stream
+    ///
+    /// The runner is returned by the previous output stream's callback when the stream is dropped,
+    /// if it doesn't arrive then a new processor is created.
+    fn take_runner(&mut self) -> ProcessorRunner<P> {
+        self.runner
+            .take()
+            .or_else(|| {
+                self.runner_return_receiver
+                    .recv_timeout(self.return_timeout)
+                    .ok()
+            })
+            .unwrap_or_else(|| {
+                warn!("The processor wasn't returned by the previous output stream");
+                ProcessorRunner::new(
+                    (self.create_processor)(self.sample_rate),
+                    self.detached_taps.detached(),
+                    self.channels,
+                    self.sample_rate,
+                    self.to_processor.clone(),
+                    self.from_processor.clone(),
+                    self.error_sender.clone(),
+                    self.shared.clone(),
+                )
+            })
+    }
+}
+
+/// Where a [CallbackProcessor] gets its input from
+pub(crate) enum CallbackInput {
+    /// The input stream's channel
+    Stream(ResamplingCons<f32>),
+    /// Interleaved samples that have been injected by a `TestBackend`
+    Injected(rtrb::Consumer<f32>),
+    /// Output-only streams process silent buffers
+    None,
+}
+
+/// Runs the processor on demand from an output stream's callback
+///
+/// Input is pulled from the [CallbackInput], and when the callback is dropped along with its
+/// stream the processor is sent back to the [CallbackProcessorState].
+pub(crate) struct CallbackProcessor<P: AudioProcessor> {
+    runner: Option<ProcessorRunner<P>>,
+    runner_return: Sender<ProcessorRunner<P>>,
+    channels: usize,
+    output_channels: usize,
+    output_map: OutputMap,
+    frames_per_update: usize,
+    input: CallbackInput,
+    error_sender: Sender<Error>,
+    shared: Arc<SharedStreamState>,
+    buffer: Vec<f32>,
+}
+
+impl<P: AudioProcessor> CallbackProcessor<P> {
+    /// Creates a callback processor with the runner that's owned by the state
+    pub fn new(
+        state: &mut CallbackProcessorState<P>,
+        processor_config: &ProcessorConfig,
+        output_channels: usize,
+        error_sender: Sender<Error>,
+        input: CallbackInput,
+    ) -> Result<Self> {
+        let output_map = OutputMap::new(
+            &processor_config.routing,
+            output_channels,
+            processor_config.channels,
+        )?;
+
+        Ok(Self {
+            runner: Some(state.take_runner()),
+            runner_return: state.runner_return_sender.clone(),
+            channels: processor_config.channels,
+            output_channels,
+            output_map,
+            frames_per_update: processor_config.frames_per_update,
+            input,
+            error_sender,
+            shared: state.shared.clone(),
+            buffer: vec![0.0; processor_config.frames_per_update * processor_config.channels],
+        })
+    }
+
+    /// Fills the interleaved output buffer with processed audio
+    pub fn process(&mut self, data: &mut [f32]) {
+        let Some(runner) = &mut self.runner else {
+            data.fill(0.0);
+            return;
+        };
+
+        for output in data.chunks_mut(self.frames_per_update * self.output_channels) {
+            let frames = output.len() / self.output_channels;
+            let buffer = &mut self.buffer[..frames * self.channels];
+
+            match &mut self.input {
+                CallbackInput::Stream(from_input) => {
+                    match from_input.read_interleaved(buffer) {
+                        ReadStatus::Ok => {}
+                        ReadStatus::InputNotReady => buffer.fill(0.0),
+                        ReadStatus::UnderflowOccurred { num_frames_read } => {
+                            self.error_sender
+                                .try_send(Error::StreamReadUnderflow {
+                                    channel: StreamChannel::InputToProcessor,
+                                    frames: num_frames_read,
+                                })
+                                .ok();
+                        }
+                        ReadStatus::OverflowCorrected {
+                            num_frames_discarded,
+                        } => {
+                            self.error_sender
+                                .try_send(Error::StreamReadOverflow {
+                                    channel: StreamChannel::InputToProcessor,
+                                    frames: num_frames_discarded,
+                                })
+                                .ok();
+                        }
+                    }
+                    self.shared.record_resampler_latency(
+                        StreamChannel::InputToProcessor,
+                        from_input.occupied_seconds(),
+                    );
+                }
+                // Silence is used once the injected input runs out
+                CallbackInput::Injected(injected) => {
+                    for sample in buffer.iter_mut() {
+                        *sample = injected.pop().unwrap_or(0.0);
+                    }
+                }
+                CallbackInput::None => buffer.fill(0.0),
+            }
+
+            runner.process(buffer, true);
+
+            if self.output_map.is_identity() {
+                output.copy_from_slice(buffer);
+            } else {
+                self.output_map.apply(buffer, output);
+            }
+        }
+    }
+
+    /// Drops the processor without returning it to the state
+    ///
+    /// This simulates an output stream that's lost along with its processor, see
+    /// `TestBackend::recreate_processor`.
+    pub fn discard_processor(&mut self) {
+        self.runner.take();
+    }
+}
+
+impl<P: AudioProcessor> Drop for CallbackProcessor<P> {
+    fn drop(&mut self) {
+        if let Some(runner) = self.runner.take() {
+            self.runner_return.send(runner).ok();
+        }
+    }
+}
+
+/// Sets up an output stream that runs the processor in its callback
+///
+/// The output stream runs at the processor's sample rate.
+pub(crate) fn initialize_callback_output_stream<P: AudioProcessor>(
+    device: Device,
+    channels: usize,
+    processor_config: &ProcessorConfig,
+    frames_per_update: u32,
+    state: &mut CallbackProcessorState<P>,
+    error_sender: Sender<Error>,
+    from_input: Option<ResamplingCons<f32>>,
+) -> Result<Stream> {
+    if channels == 0 {
+        return Err(Error::DeviceHasNoAvailableChannels {
+            device_name: device.name().unwrap_or_default(),
+            stream: "output",
+        });
+    }
+
+    let config = DeviceStreamConfig {
+        channels: channels as u16,
+        sample_rate: SampleRate(processor_config.sample_rate as u32),
+        buffer_size: BufferSize::Fixed(frames_per_update),
+    };
+    debug!("Setting up callback output stream with config: {config:?}");
+
+    let input = match from_input {
+        Some(from_input) => CallbackInput::Stream(from_input),
+        None => CallbackInput::None,
+    };
+    let mut callback_processor =
+        CallbackProcessor::new(state, processor_config, channels, error_sender, input)?;
+
+    let result = device.build_output_stream(
+        &config,
+        move |data: &mut [f32], _info: &OutputCallbackInfo| callback_processor.process(data),
+        move |err| error!("Error on audio output stream: {}", err),
+        None,
+    )?;
+
+    Ok(result)
+}
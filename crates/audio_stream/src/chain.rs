@@ -0,0 +1,514 @@
+use audio_module::{
+    AudioModule, AudioProcessor, Parameter, Parameters, PopMessage, PushMessage, ToProcessor,
+};
+use std::{cell::RefCell, iter, marker::PhantomData};
+
+/// The number of parameter ids that are reserved for each module in a [ModuleChain]
+pub const MODULE_PARAMETER_STRIDE: usize = 1 << 16;
+
+/// The number of messages that can be queued for each module
+const MESSAGE_QUEUE_CAPACITY: usize = 1024;
+
+/// The address of a parameter within a [ModuleChain]
+///
+/// Modules are indexed in processing order, with nested chains flattened, so the modules in
+/// `ModuleChain<A, ModuleChain<B, C>>` are at indices 0, 1, and 2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParameterAddress {
+    pub module: usize,
+    pub parameter: usize,
+}
+
+impl ParameterAddress {
+    pub const fn new(module: usize, parameter: usize) -> Self {
+        Self { module, parameter }
+    }
+
+    /// The chain's parameter id for the address, for use in `ToProcessor` messages
+    pub const fn id(&self) -> usize {
+        self.module * MODULE_PARAMETER_STRIDE + self.parameter
+    }
+
+    /// Converts a chain's parameter id into an address
+    pub const fn from_id(id: usize) -> Self {
+        Self {
+            module: id / MODULE_PARAMETER_STRIDE,
+            parameter: id % MODULE_PARAMETER_STRIDE,
+        }
+    }
+}
+
+/// An `AudioModule` that processes audio with `First` followed by `Second`
+///
+/// Longer chains are built by nesting, e.g. `ModuleChain<Eq, ModuleChain<Delay, FreeverbModule>>`.
+///
+/// Each of the chain's modules has its own range of parameter ids, see [ParameterAddress].
+/// `ToProcessor` messages are routed to the module that owns the parameter, with the parameter's
+/// id converted back into the module's own id. Messages from the modules' processors are merged
+/// into [ChainMessage]s.
+///
+/// Each module has a fixed-capacity queue for its messages so that routing doesn't allocate on
+/// the audio thread. If a module's queue is full then routing stops until the module has
+/// received its queued messages, leaving any further messages in the stream's channel.
+pub struct ModuleChain<First, Second> {
+    _modules: PhantomData<(First, Second)>,
+}
+
+impl<First, Second> AudioModule for ModuleChain<First, Second>
+where
+    First: AudioModule,
+    Second: AudioModule,
+{
+    const NAME: &'static str = "Module Chain";
+
+    // The second module receives the output of the first
+    const REQUIRES_INPUT: bool = First::REQUIRES_INPUT;
+
+    const MODULES: usize = First::MODULES + Second::MODULES;
+
+    type Parameters = ChainParameters<First, Second>;
+    type Processor = ChainProcessor<First::Processor, Second::Processor>;
+
+    fn create_processor(sample_rate: usize) -> Self::Processor {
+        ChainProcessor {
+            first: First::create_processor(sample_rate),
+            second: Second::create_processor(sample_rate),
+            second_offset: First::MODULES * MODULE_PARAMETER_STRIDE,
+            first_messages: ChainMessages::default(),
+            second_messages: ChainMessages::default(),
+            pending_message: None,
+        }
+    }
+}
+
+/// The parameters of a [ModuleChain]'s modules
+///
+/// The second module's parameters have their ids offset so that they match the chain's parameter
+/// ids, see [ParameterAddress]. Every parameter's key is prefixed with its module's index, e.g.
+/// `0/dry` or `2/wet`, so that keys remain unique within the chain.
+pub struct ChainParameters<First: AudioModule, Second: AudioModule> {
+    pub first: First::Parameters,
+    pub second: Second::Parameters,
+}
+
+impl<First: AudioModule, Second: AudioModule> Default for ChainParameters<First, Second> {
+    fn default() -> Self {
+        let mut first = First::Parameters::default();
+        prefix_parameters::<First>(&mut first, 0);
+
+        let mut second = Second::Parameters::default();
+        prefix_parameters::<Second>(&mut second, First::MODULES * MODULE_PARAMETER_STRIDE);
+
+        Self { first, second }
+    }
+}
+
+/// Offsets the ids of a module's parameters, and prefixes their keys with their module's index
+fn prefix_parameters<M: AudioModule>(parameters: &mut M::Parameters, id_offset: usize) {
+    for parameter in parameters.parameters_mut() {
+        let id = parameter.id() + id_offset;
+        parameter.set_id(id);
+
+        // Nested chains have already prefixed their keys, so their prefixes are replaced
+        let key = parameter.key();
+        let key = match key.split_once('/') {
+            Some((_, key)) if M::MODULES > 1 => key,
+            _ => key.as_str(),
+        };
+        parameter.set_key(&format!("{}/{key}", ParameterAddress::from_id(id).module));
+    }
+}
+
+impl<First, Second> Clone for ChainParameters<First, Second>
+where
+    First: AudioModule<Parameters: Clone>,
+    Second: AudioModule<Parameters: Clone>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            first: self.first.clone(),
+            second: self.second.clone(),
+        }
+    }
+}
+
+impl<First: AudioModule, Second: AudioModule> Parameters for ChainParameters<First, Second> {
+    fn parameters(&self) -> Vec<&dyn Parameter> {
+        let mut parameters = self.first.parameters();
+        parameters.extend(self.second.parameters());
+        parameters
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut dyn Parameter> {
+        let mut parameters = self.first.parameters_mut();
+        parameters.extend(self.second.parameters_mut());
+        parameters
+    }
+}
+
+/// A message from one of a [ModuleChain]'s processors
+#[derive(Clone, Debug)]
+#[cfg_attr(target_arch = "wasm32", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChainMessage<First, Second> {
+    First(First),
+    Second(Second),
+}
+
+/// The processor for a [ModuleChain]
+pub struct ChainProcessor<First, Second> {
+    first: First,
+    second: Second,
+    // The first parameter id that belongs to the second processor
+    second_offset: usize,
+    first_messages: ChainMessages,
+    second_messages: ChainMessages,
+    // A message that didn't fit in its module's queue, routed before any new messages
+    pending_message: Option<ToProcessor>,
+}
+
+impl<First, Second> AudioProcessor for ChainProcessor<First, Second>
+where
+    First: AudioProcessor,
+    Second: AudioProcessor,
+{
+    type OutputMessage = ChainMessage<First::OutputMessage, Second::OutputMessage>;
+
+    fn process_buffer<To, From>(
+        &mut self,
+        buffer: &mut [f32],
+        channels: usize,
+        to_processor: &To,
+        from_processor: &From,
+    ) where
+        To: PopMessage<ToProcessor>,
+        From: PushMessage<Self::OutputMessage>,
+    {
+        let pending_message = self.pending_message.take();
+        for message in pending_message
+            .into_iter()
+            .chain(iter::from_fn(|| to_processor.pop()))
+        {
+            let id = parameter_id(&message);
+            let messages = if id < self.second_offset {
+                &self.first_messages
+            } else {
+                &self.second_messages
+            };
+
+            if messages.is_full() {
+                self.pending_message = Some(message);
+                break;
+            }
+
+            if id < self.second_offset {
+                messages.push(message);
+            } else {
+                messages.push(with_parameter_id(message, id - self.second_offset));
+            }
+        }
+
+        self.first.process_buffer(
+            buffer,
+            channels,
+            &self.first_messages,
+            &ChainSender::new(from_processor, ChainMessage::First),
+        );
+        self.second.process_buffer(
+            buffer,
+            channels,
+            &self.second_messages,
+            &ChainSender::new(from_processor, ChainMessage::Second),
+        );
+    }
+}
+
+fn parameter_id(message: &ToProcessor) -> usize {
+    match message {
+        ToProcessor::BeginEdit(id)
+        | ToProcessor::SetParameter(id, _)
+        | ToProcessor::EndEdit(id) => *id,
+    }
+}
+
+fn with_parameter_id(message: ToProcessor, id: usize) -> ToProcessor {
+    match message {
+        ToProcessor::BeginEdit(_) => ToProcessor::BeginEdit(id),
+        ToProcessor::SetParameter(_, value) => ToProcessor::SetParameter(id, value),
+        ToProcessor::EndEdit(_) => ToProcessor::EndEdit(id),
+    }
+}
+
+/// Messages that have been routed to one of the chain's processors
+///
+/// The messages are stored in a ring of [MESSAGE_QUEUE_CAPACITY] slots that's allocated up front.
+struct ChainMessages {
+    ring: RefCell<MessageRing>,
+}
+
+struct MessageRing {
+    slots: Box<[Option<ToProcessor>]>,
+    start: usize,
+    len: usize,
+}
+
+impl Default for ChainMessages {
+    fn default() -> Self {
+        Self {
+            ring: RefCell::new(MessageRing {
+                slots: vec![None; MESSAGE_QUEUE_CAPACITY].into_boxed_slice(),
+                start: 0,
+                len: 0,
+            }),
+        }
+    }
+}
+
+impl ChainMessages {
+    fn is_full(&self) -> bool {
+        let ring = self.ring.borrow();
+        ring.len == ring.slots.len()
+    }
+
+    /// Adds a message to the end of the queue, which must not be full
+    fn push(&self, message: ToProcessor) {
+        let mut ring = self.ring.borrow_mut();
+        debug_assert!(ring.len < ring.slots.len());
+
+        let index = (ring.start + ring.len) % ring.slots.len();
+        ring.slots[index] = Some(message);
+        ring.len += 1;
+    }
+}
+
+impl PopMessage<ToProcessor> for ChainMessages {
+    fn pop(&self) -> Option<ToProcessor> {
+        let mut ring = self.ring.borrow_mut();
+        if ring.len == 0 {
+            return None;
+        }
+
+        let index = ring.start;
+        ring.start = (index + 1) % ring.slots.len();
+        ring.len -= 1;
+        ring.slots[index].take()
+    }
+}
+
+/// Wraps a processor's output messages in [ChainMessage]s before passing them on
+struct ChainSender<'a, P, T, U> {
+    sender: &'a P,
+    wrap: fn(T) -> U,
+}
+
+impl<'a, P, T, U> ChainSender<'a, P, T, U> {
+    fn new(sender: &'a P, wrap: fn(T) -> U) -> Self {
+        Self { sender, wrap }
+    }
+}
+
+impl<P, T, U> PushMessage<T> for ChainSender<'_, P, T, U>
+where
+    P: PushMessage<U>,
+    T: Send,
+    U: Send,
+{
+    fn push(&self, message: T) -> bool {
+        self.sender.push((self.wrap)(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audio_module::BoolParameter;
+    use std::collections::VecDeque;
+
+    /// The number of messages that a [TestProcessor] receives per block
+    const MESSAGES_PER_BLOCK: usize = 256;
+
+    /// A module that passes the messages it receives back out of its processor
+    struct TestModule;
+
+    struct TestParameters {
+        enabled: BoolParameter,
+        invert: BoolParameter,
+    }
+
+    impl Default for TestParameters {
+        fn default() -> Self {
+            Self {
+                enabled: BoolParameter::new("Enabled", 0, true),
+                invert: BoolParameter::new("Invert", 1, false),
+            }
+        }
+    }
+
+    impl Parameters for TestParameters {
+        fn parameters(&self) -> Vec<&dyn Parameter> {
+            vec![&self.enabled, &self.invert]
+        }
+
+        fn parameters_mut(&mut self) -> Vec<&mut dyn Parameter> {
+            vec![&mut self.enabled, &mut self.invert]
+        }
+    }
+
+    struct TestProcessor;
+
+    impl AudioModule for TestModule {
+        const NAME: &'static str = "Test";
+
+        type Parameters = TestParameters;
+        type Processor = TestProcessor;
+
+        fn create_processor(_sample_rate: usize) -> Self::Processor {
+            TestProcessor
+        }
+    }
+
+    impl AudioProcessor for TestProcessor {
+        type OutputMessage = ToProcessor;
+
+        fn process_buffer<To, From>(
+            &mut self,
+            _buffer: &mut [f32],
+            _channels: usize,
+            to_processor: &To,
+            from_processor: &From,
+        ) where
+            To: PopMessage<ToProcessor>,
+            From: PushMessage<Self::OutputMessage>,
+        {
+            for message in iter::from_fn(|| to_processor.pop()).take(MESSAGES_PER_BLOCK) {
+                from_processor.push(message);
+            }
+        }
+    }
+
+    struct TestChannel<T> {
+        messages: RefCell<VecDeque<T>>,
+    }
+
+    impl<T> Default for TestChannel<T> {
+        fn default() -> Self {
+            Self {
+                messages: RefCell::new(VecDeque::new()),
+            }
+        }
+    }
+
+    impl<T: Send> PushMessage<T> for TestChannel<T> {
+        fn push(&self, message: T) -> bool {
+            self.messages.borrow_mut().push_back(message);
+            true
+        }
+    }
+
+    impl<T: Send> PopMessage<T> for TestChannel<T> {
+        fn pop(&self) -> Option<T> {
+            self.messages.borrow_mut().pop_front()
+        }
+    }
+
+    type NestedChain = ModuleChain<TestModule, ModuleChain<TestModule, TestModule>>;
+    type NestedOutput = <<NestedChain as AudioModule>::Processor as AudioProcessor>::OutputMessage;
+
+    fn process<M: AudioModule>(
+        processor: &mut M::Processor,
+        to_processor: &TestChannel<ToProcessor>,
+    ) -> Vec<<M::Processor as AudioProcessor>::OutputMessage> {
+        let from_processor = TestChannel::default();
+        processor.process_buffer(&mut [0.0; 8], 2, to_processor, &from_processor);
+        from_processor.messages.into_inner().into()
+    }
+
+    fn ids_and_keys<M: AudioModule>() -> Vec<(usize, String)> {
+        M::Parameters::default()
+            .parameters()
+            .iter()
+            .map(|parameter| (parameter.id(), parameter.key().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parameter_addresses_round_trip_through_ids() {
+        let address = ParameterAddress::new(2, 1);
+        assert_eq!(address.id(), 2 * MODULE_PARAMETER_STRIDE + 1);
+        assert_eq!(ParameterAddress::from_id(address.id()), address);
+
+        assert_eq!(ParameterAddress::from_id(5), ParameterAddress::new(0, 5));
+        assert_eq!(
+            ParameterAddress::from_id(MODULE_PARAMETER_STRIDE),
+            ParameterAddress::new(1, 0)
+        );
+    }
+
+    #[test]
+    fn every_module_has_prefixed_keys_and_offset_ids() {
+        let stride = MODULE_PARAMETER_STRIDE;
+        let expected = vec![
+            (0, "0/enabled".to_string()),
+            (1, "0/invert".to_string()),
+            (stride, "1/enabled".to_string()),
+            (stride + 1, "1/invert".to_string()),
+            (stride * 2, "2/enabled".to_string()),
+            (stride * 2 + 1, "2/invert".to_string()),
+        ];
+
+        // Nesting on either side gives the same flattened addresses
+        assert_eq!(ids_and_keys::<NestedChain>(), expected);
+        assert_eq!(
+            ids_and_keys::<ModuleChain<ModuleChain<TestModule, TestModule>, TestModule>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn messages_are_routed_to_the_parameters_module() {
+        let mut processor = NestedChain::create_processor(44100);
+        let to_processor = TestChannel::default();
+        to_processor.push(ToProcessor::SetParameter(
+            ParameterAddress::new(2, 1).id(),
+            0.5,
+        ));
+        to_processor.push(ToProcessor::BeginEdit(ParameterAddress::new(0, 0).id()));
+        to_processor.push(ToProcessor::EndEdit(ParameterAddress::new(1, 1).id()));
+
+        let output = process::<NestedChain>(&mut processor, &to_processor);
+
+        // Messages arrive with the module's own ids, in processing order
+        assert!(matches!(
+            output.as_slice(),
+            [
+                ChainMessage::First(ToProcessor::BeginEdit(0)),
+                ChainMessage::Second(ChainMessage::First(ToProcessor::EndEdit(1))),
+                ChainMessage::Second(ChainMessage::Second(ToProcessor::SetParameter(1, 0.5))),
+            ]
+        ));
+    }
+
+    #[test]
+    fn messages_wait_for_space_in_a_full_queue() {
+        let message_count = MESSAGE_QUEUE_CAPACITY * 2 + 10;
+
+        let mut processor = NestedChain::create_processor(44100);
+        let to_processor = TestChannel::default();
+        for index in 0..message_count {
+            to_processor.push(ToProcessor::SetParameter(0, index as f32));
+        }
+
+        let mut received: Vec<NestedOutput> = Vec::new();
+        for _ in 0..message_count.div_ceil(MESSAGES_PER_BLOCK) {
+            received.extend(process::<NestedChain>(&mut processor, &to_processor));
+        }
+
+        // All of the messages are delivered, in the order they were sent
+        assert_eq!(received.len(), message_count);
+        for (index, message) in received.iter().enumerate() {
+            assert!(matches!(
+                message,
+                ChainMessage::First(ToProcessor::SetParameter(0, value)) if *value == index as f32
+            ));
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+/// The default number of frames that are processed in each processor update
+pub const DEFAULT_FRAMES_PER_UPDATE: usize = 128;
+
+/// The sample rate that a stream's processor runs at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProcessorSampleRate {
+    /// The processor runs at a fixed sample rate, with resampling applied to and from the devices
+    /// when their sample rates differ.
+    Fixed(usize),
+    /// The processor runs at the output device's sample rate.
+    ///
+    /// The sample rate is determined when the stream is created, if the output device changes
+    /// while the stream is running then resampling will be applied when necessary.
+    FollowOutput,
+}
+
+/// Where a stream's processor gets run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProcessingMode {
+    /// The processor runs on its own thread, with resampling channels to and from the devices.
+    #[default]
+    Threaded,
+    /// The processor runs inside the output device's callback, with lower latency and jitter.
+    ///
+    /// Input is pulled from a lock-free ring that's filled by the input device's callback, the
+    /// lowest latency is achieved when the input and output share a device and sample rate.
+    /// The output device needs to support the processor's sample rate, so this mode works best
+    /// with [ProcessorSampleRate::FollowOutput].
+    Callback,
+}
+
+/// The quality of the resampling that's applied between the devices and the processor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResamplerQuality {
+    /// Lower CPU usage and latency
+    #[default]
+    Low,
+    /// Better quality at the cost of more CPU usage and latency
+    High,
+}
+
+/// Configures the resampling channels between the devices and the processor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResamplerConfig {
+    pub quality: ResamplerQuality,
+    /// The target latency of each channel
+    ///
+    /// When `None`, the latency is derived from the device and processor update durations.
+    pub latency: Option<Duration>,
+    /// The capacity of each channel, which must be larger than the channel's latency
+    ///
+    /// When `None`, the capacity is 16x the channel's latency.
+    pub capacity: Option<Duration>,
+}
+
+impl Default for ResamplerConfig {
+    fn default() -> Self {
+        Self {
+            quality: ResamplerQuality::Low,
+            latency: None,
+            capacity: None,
+        }
+    }
+}
+
+/// The gain that's applied when several processor channels are mixed into one device channel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DownmixLaw {
+    /// The channels are summed without any gain compensation
+    Sum,
+    /// The channels are averaged, i.e. -6dB for two channels
+    Average,
+    /// The channels are scaled to preserve their combined power, i.e. -3dB for two channels
+    #[default]
+    EqualPower,
+}
+
+impl DownmixLaw {
+    /// The gain that's applied to each of the given number of channels when they're mixed together
+    pub fn gain(&self, channels: usize) -> f32 {
+        let channels = channels.max(1) as f32;
+        match self {
+            Self::Sum => 1.0,
+            Self::Average => 1.0 / channels,
+            Self::EqualPower => 1.0 / channels.sqrt(),
+        }
+    }
+}
+
+/// Maps the device's channels to and from the processor's channels
+///
+/// Channel indices start at 0.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChannelRouting {
+    /// The input device channel that feeds each processor channel, `None` for silence
+    ///
+    /// When empty, each processor channel is fed by the matching input channel, with mono inputs
+    /// feeding all of the processor's channels.
+    pub input: Vec<Option<usize>>,
+    /// The output device channel that receives each processor channel, `None` to discard it
+    ///
+    /// When empty, each processor channel is sent to the matching output channel, with all of the
+    /// processor's channels mixed together for mono outputs. Output channels that don't receive a
+    /// processor channel are silent.
+    pub output: Vec<Option<usize>>,
+    /// The law that's used when processor channels are mixed into the same output channel
+    pub downmix: DownmixLaw,
+}
+
+/// Configures the processing performed by an `AudioStream`
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamConfig {
+    /// The sample rate that the processor runs at
+    pub sample_rate: ProcessorSampleRate,
+    /// The number of frames that are passed to the processor in each update
+    pub frames_per_update: usize,
+    /// The number of channels that are passed to the processor
+    pub channels: usize,
+    /// Where the processor gets run
+    pub mode: ProcessingMode,
+    /// Runs the stream without an input device, with the processor receiving silent buffers
+    ///
+    /// Streams for modules that don't require input are always output-only,
+    /// see `AudioModule::REQUIRES_INPUT`.
+    pub output_only: bool,
+    /// Configures the resampling between the devices and the processor
+    pub resampler: ResamplerConfig,
+    /// Maps the device's channels to and from the processor's channels
+    ///
+    /// Routing isn't supported by JACK clients, which have a port for each processor channel.
+    pub routing: ChannelRouting,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: ProcessorSampleRate::Fixed(44100),
+            frames_per_update: DEFAULT_FRAMES_PER_UPDATE,
+            channels: 2,
+            mode: ProcessingMode::Threaded,
+            output_only: false,
+            resampler: ResamplerConfig::default(),
+            routing: ChannelRouting::default(),
+        }
+    }
+}
@@ -1,26 +1,65 @@
 #[cfg(target_os = "android")]
 mod android;
+mod callback;
+mod devices;
+mod input_source;
 #[cfg(target_os = "ios")]
 mod ios;
+#[cfg(all(target_os = "linux", feature = "jack"))]
+mod jack_backend;
+mod offline;
+mod recorder;
+mod routing;
+mod runner;
+mod stats;
+mod test_backend;
+
+pub use cpal::HostId;
+pub use devices::{
+    DeviceCapabilities, DeviceDirection, DeviceInfo, DevicePolicy, DeviceSelection, HostInfo,
+    available_devices, available_hosts,
+};
+pub use input_source::{InputSource, TestSignal};
+pub use offline::{OfflineStream, ScheduledMessage};
+pub use stats::{
+    ChannelStats, DeviceConfig, ResamplerStats, StreamChannel, StreamEvent, StreamStats, XrunKind,
+};
+pub use test_backend::TestBackend;
 
-use crate::FRAMES_PER_UPDATE;
+use crate::{
+    ChannelRouting, ProcessingMode, ProcessorLoad, ProcessorSampleRate, ResamplerConfig,
+    ResamplerQuality, StreamConfig,
+};
 use audio_module::{AudioModule, AudioProcessor, PopMessage, PushMessage, ToProcessor};
 use audio_thread_priority::promote_current_thread_to_real_time;
+use callback::{
+    CallbackInput, CallbackProcessor, CallbackProcessorState, initialize_callback_output_stream,
+};
 use cpal::{
-    BufferSize, Device, InputCallbackInfo, OutputCallbackInfo, SampleRate, Stream, StreamConfig,
-    SupportedBufferSize, SupportedStreamConfig,
-    traits::{DeviceTrait, HostTrait, StreamTrait},
+    BufferSize, Device, Host, InputCallbackInfo, OutputCallbackInfo, SampleRate, Stream,
+    StreamConfig as DeviceStreamConfig, SupportedBufferSize, SupportedStreamConfig,
+    traits::{DeviceTrait, StreamTrait},
 };
 use crossbeam_channel::{Receiver, Sender, bounded, unbounded};
+use devices::{DeviceSettings, DeviceWatcher};
 use fixed_resample::{
     PushStatus, ReadStatus, ResampleQuality, ResamplingChannelConfig, ResamplingCons,
     ResamplingProd, resampling_channel,
 };
+use input_source::{InputSourceControl, InputSourcePlayer};
+#[cfg(all(target_os = "linux", feature = "jack"))]
+use jack_backend::{JackClient, OpenJackClient};
 use log::{debug, error, info, warn};
+use recorder::{Recorder, RecordingTap};
+use routing::{InputMap, OutputMap};
+use runner::ProcessorRunner;
+use stats::{SharedStreamState, StartedStreams};
 use std::{
+    marker::PhantomData,
     num::NonZeroUsize,
+    path::Path,
     sync::{
-        Arc,
+        Arc, Mutex, PoisonError,
         atomic::{AtomicBool, Ordering},
     },
     thread::{self, JoinHandle, sleep},
@@ -28,33 +67,316 @@ use std::{
 };
 use thiserror::Error;
 
-const CHANNELS: usize = 2;
+/// The maximum number of channels that can be passed to the processor
+pub const MAX_CHANNELS: usize = 8;
 
 pub struct AudioStream<M: AudioModule> {
     to_processor: ToProcessorSender,
     from_processor: FromProcessorReceiver<M::Processor>,
     sample_rate: usize,
+    shared: Arc<SharedStreamState>,
+    events: Receiver<StreamEvent>,
+    recorder: Mutex<Recorder>,
+    input_source: InputSourceControl,
+    #[cfg(all(target_os = "linux", feature = "jack"))]
+    jack_client: Option<JackClient<M::Processor>>,
     exit_flag: Arc<AtomicBool>,
     processor_thread: Option<JoinHandle<()>>,
     stream_manager_thread: Option<JoinHandle<()>>,
 }
 
 impl<M: AudioModule> AudioStream<M> {
-    pub fn new() -> Result<Self> {
+    /// Creates a stream with the given config using the default host and devices
+    pub fn new(config: StreamConfig) -> Result<Self> {
+        Self::builder().config(config).build()
+    }
+
+    /// Returns a builder that allows the stream's host and devices to be chosen
+    pub fn builder() -> AudioStreamBuilder<M> {
+        AudioStreamBuilder {
+            config: StreamConfig::default(),
+            device_settings: DeviceSettings::default(),
+            #[cfg(all(target_os = "linux", feature = "jack"))]
+            jack: false,
+            _module: PhantomData,
+        }
+    }
+
+    pub fn to_processor(&self) -> ToProcessorSender {
+        self.to_processor.clone()
+    }
+
+    pub fn from_processor(&self) -> FromProcessorReceiver<M::Processor> {
+        self.from_processor.clone()
+    }
+
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+
+    /// Returns the estimated round-trip latency from the input device to the output device
+    ///
+    /// The estimate includes the device buffers and the buffering between the devices and the
+    /// processor. `None` is returned while the device streams aren't running.
+    pub fn latency(&self) -> Option<Duration> {
+        self.shared.latency()
+    }
+
+    /// Returns a snapshot of the processor's load, measured against each block's deadline
+    ///
+    /// This is a cheap lock-free read that can be polled regularly, e.g. once per UI frame.
+    pub fn processor_load(&self) -> ProcessorLoad {
+        self.shared.load_meter().snapshot()
+    }
+
+    /// Clears the processor load measurements
+    pub fn reset_processor_load(&self) {
+        self.shared.load_meter().reset();
+    }
+
+    /// Returns a snapshot of the stream's health
+    pub fn stats(&self) -> StreamStats {
+        self.shared.stats()
+    }
+
+    /// Returns a receiver for the events that are reported while the stream is running
+    ///
+    /// Events are dropped when the receiver's buffer is full, so the receiver should be polled
+    /// regularly, e.g. once per UI frame.
+    pub fn events(&self) -> Receiver<StreamEvent> {
+        self.events.clone()
+    }
+
+    /// Starts recording the processor's output to a WAV file
+    ///
+    /// The file is written as 32-bit float audio at the processor's sample rate. If
+    /// `include_input` is true then the processor's dry input is recorded alongside the output,
+    /// with the input channels following the output channels in each frame. Input isn't recorded
+    /// for output-only streams.
+    ///
+    /// Audio is passed from the processor to a separate writer thread, if the writer thread falls
+    /// behind then blocks are dropped from the recording and reported as errors.
+    ///
+    /// `Error::StreamNotRunning` is returned if the stream's devices aren't currently running, and
+    /// `Error::RecorderCommandSendFailed` if the processor isn't picking up commands.
+    pub fn start_recording(&self, path: impl AsRef<Path>, include_input: bool) -> Result<()> {
+        self.recorder
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .start(path.as_ref(), include_input)
+    }
+
+    /// Stops the current recording and finalizes its file
+    pub fn stop_recording(&self) -> Result<()> {
+        self.recorder
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .stop()
+            .map(|_| ())
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_recording()
+    }
+
+    /// Sets where the audio that's passed to the processor comes from
+    ///
+    /// Files and test signals replace the input device's audio while keeping the same output
+    /// path, and can also be used with output-only streams. Files are decoded on the calling
+    /// thread before being handed to the processor.
+    ///
+    /// Sources are queued until the processor picks them up, if the queue is full (e.g. because
+    /// the stream's devices aren't running) then `Error::InputSourceSendFailed` is returned.
+    pub fn set_input_source(&self, source: InputSource) -> Result<()> {
+        self.input_source.set(source)
+    }
+}
+
+impl<M: AudioModule> Drop for AudioStream<M> {
+    fn drop(&mut self) {
+        // Deactivating the JACK client stops its process callback
+        #[cfg(all(target_os = "linux", feature = "jack"))]
+        self.jack_client.take();
+
+        self.exit_flag.store(true, Ordering::Relaxed);
+
+        self.processor_thread.take().map(JoinHandle::join);
+        self.stream_manager_thread.take().map(JoinHandle::join);
+    }
+}
+
+/// A builder for [AudioStream], see [AudioStream::builder]
+pub struct AudioStreamBuilder<M: AudioModule> {
+    config: StreamConfig,
+    device_settings: DeviceSettings,
+    #[cfg(all(target_os = "linux", feature = "jack"))]
+    jack: bool,
+    _module: PhantomData<M>,
+}
+
+impl<M: AudioModule> AudioStreamBuilder<M> {
+    /// Returns the audio hosts that are available on the current platform
+    pub fn available_hosts(&self) -> Vec<HostInfo> {
+        available_hosts()
+    }
+
+    /// Returns the devices that are available for the builder's host
+    pub fn available_devices(&self) -> Result<Vec<DeviceInfo>> {
+        available_devices(self.device_settings.host)
+    }
+
+    /// Sets the config that should be used for processing
+    pub fn config(mut self, config: StreamConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sets the host that should be used, by default the platform's default host is used
+    pub fn host(mut self, host: HostId) -> Self {
+        self.device_settings.host = Some(host);
+        self
+    }
+
+    /// Sets the device that should be used for input
+    pub fn input_device(mut self, device: DeviceSelection) -> Self {
+        self.device_settings.input = device;
+        self
+    }
+
+    /// Sets the device that should be used for output
+    pub fn output_device(mut self, device: DeviceSelection) -> Self {
+        self.device_settings.output = device;
+        self
+    }
+
+    /// Sets what the stream does when its devices change, see [DevicePolicy]
+    pub fn device_policy(mut self, policy: DevicePolicy) -> Self {
+        self.device_settings.policy = policy;
+        self
+    }
+
+    /// Sets whether or not a JACK client should be used, disabled by default
+    ///
+    /// The client is named after `AudioModule::NAME`, and the processor runs in the JACK process
+    /// callback at the server's sample rate and buffer size. If the JACK server isn't running then
+    /// the builder's host and devices are used instead.
+    ///
+    /// When the JACK client is used, the config's mode needs to be [ProcessingMode::Callback], the
+    /// sample rate needs to be [ProcessorSampleRate::FollowOutput] or match the server's, and the
+    /// default routing, devices, and device policy need to be used, otherwise
+    /// [Error::InvalidStreamConfig] is returned.
+    #[cfg(all(target_os = "linux", feature = "jack"))]
+    pub fn jack(mut self, enabled: bool) -> Self {
+        self.jack = enabled;
+        self
+    }
+
+    pub fn build(self) -> Result<AudioStream<M>> {
+        let config = self.config;
+        let device_settings = self.device_settings;
+
+        if config.channels == 0 || config.channels > MAX_CHANNELS {
+            return Err(Error::InvalidStreamConfig {
+                reason: "the channel count must be between 1 and MAX_CHANNELS",
+            });
+        }
+        if config.frames_per_update == 0 {
+            return Err(Error::InvalidStreamConfig {
+                reason: "at least one frame must be processed per update",
+            });
+        }
+        if config
+            .resampler
+            .latency
+            .is_some_and(|latency| latency.is_zero())
+        {
+            return Err(Error::InvalidStreamConfig {
+                reason: "the resampler latency must be greater than zero",
+            });
+        }
+        if let (Some(latency), Some(capacity)) =
+            (config.resampler.latency, config.resampler.capacity)
+            && capacity <= latency
+        {
+            return Err(Error::InvalidStreamConfig {
+                reason: "the resampler capacity must be larger than its latency",
+            });
+        }
+
+        for routes in [&config.routing.input, &config.routing.output] {
+            if !routes.is_empty() && routes.len() != config.channels {
+                return Err(Error::InvalidStreamConfig {
+                    reason: "channel routes must be provided for each of the processor's channels",
+                });
+            }
+        }
+
+        let output_only = config.output_only || !M::REQUIRES_INPUT;
+
         #[cfg(target_os = "android")]
         {
-            if let Err(error) = android::request_recording_permission() {
+            if !output_only && let Err(error) = android::request_recording_permission() {
                 error!("Failed to get recording permission: {error}");
             }
         }
 
         #[cfg(target_os = "ios")]
         {
-            ios::setup_audio_session();
+            ios::setup_audio_session(!output_only);
         }
 
+        #[cfg(all(target_os = "linux", feature = "jack"))]
+        let jack_client = if self.jack {
+            match OpenJackClient::open(M::NAME) {
+                Ok(client) => {
+                    check_jack_config(&config, &device_settings, client.sample_rate())?;
+                    Some(client)
+                }
+                Err(error) => {
+                    warn!("JACK is unavailable, falling back to the default host: {error}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        // JACK clients run at the server's sample rate and buffer size
+        #[cfg(all(target_os = "linux", feature = "jack"))]
+        let jack_settings = jack_client
+            .as_ref()
+            .map(|client| (client.sample_rate(), client.buffer_size()));
+        #[cfg(not(all(target_os = "linux", feature = "jack")))]
+        let jack_settings: Option<(usize, usize)> = None;
+
         let channel_capacity = 1024;
-        let processor_sample_rate = 44100;
+        let (processor_sample_rate, frames_per_update) = match jack_settings {
+            Some(settings) => settings,
+            None => {
+                let sample_rate = match config.sample_rate {
+                    ProcessorSampleRate::Fixed(sample_rate) => sample_rate,
+                    ProcessorSampleRate::FollowOutput => {
+                        let host = device_settings.host()?;
+                        let output_config = device_settings
+                            .output_device(&host)?
+                            .default_output_config()?;
+                        output_config.sample_rate().0 as usize
+                    }
+                };
+                (sample_rate, config.frames_per_update)
+            }
+        };
+        let mode = config.mode;
+        let config = ProcessorConfig {
+            sample_rate: processor_sample_rate,
+            frames_per_update,
+            channels: config.channels,
+            output_only,
+            resampler: config.resampler,
+            routing: config.routing,
+        };
 
         let (to_processor_sender, to_processor_receiver) = bounded(channel_capacity);
         let to_processor_sender = ToProcessorSender(to_processor_sender);
@@ -67,42 +389,138 @@ impl<M: AudioModule> AudioStream<M> {
 
         let (stream_error_sender, stream_error_receiver) = bounded(channel_capacity);
 
-        let (stream_channels_sender, stream_channels_receiver) = unbounded();
+        let (event_sender, event_receiver) = bounded(channel_capacity);
+        let shared = Arc::new(SharedStreamState::new(event_sender));
         let exit_flag = Arc::new(AtomicBool::new(false));
 
+        let (recorder, recording_tap) = Recorder::new(
+            processor_sample_rate,
+            config.frames_per_update,
+            config.channels,
+            output_only,
+            shared.clone(),
+        );
+        let (input_source, input_source_player) =
+            InputSourceControl::new(processor_sample_rate, config.channels);
+        let taps = ProcessorTaps {
+            recording: recording_tap,
+            input_source: input_source_player,
+        };
+
+        #[cfg(all(target_os = "linux", feature = "jack"))]
+        if let Some(jack_client) = jack_client {
+            let shutdown_flag = Arc::new(AtomicBool::new(false));
+            let jack_client = jack_client.activate(
+                M::create_processor(processor_sample_rate),
+                config.channels,
+                output_only,
+                to_processor_receiver,
+                from_processor_sender,
+                taps,
+                stream_error_sender,
+                shared.clone(),
+                shutdown_flag.clone(),
+            )?;
+
+            // Errors from the process callback are reported by the monitor thread
+            let monitor_thread = {
+                thread::Builder::new()
+                    .name("jack_monitor".to_string())
+                    .spawn({
+                        let shared = shared.clone();
+                        let exit_flag = exit_flag.clone();
+
+                        move || {
+                            jack_backend::monitor_thread(
+                                stream_error_receiver,
+                                shared,
+                                shutdown_flag,
+                                exit_flag,
+                            );
+                        }
+                    })?
+            };
+
+            return Ok(AudioStream {
+                to_processor: to_processor_sender,
+                from_processor: from_processor_receiver,
+                sample_rate: processor_sample_rate,
+                shared,
+                events: event_receiver,
+                recorder: Mutex::new(recorder),
+                input_source,
+                jack_client: Some(jack_client),
+                exit_flag,
+                processor_thread: None,
+                stream_manager_thread: Some(monitor_thread),
+            });
+        }
+
+        let (processor_location, processor_thread) = match mode {
+            ProcessingMode::Threaded => {
+                let (stream_channels_sender, stream_channels_receiver) = unbounded();
+
+                // Start the processor thread
+                let processor_thread = {
+                    thread::Builder::new()
+                        .name("audio_processor".to_string())
+                        .spawn({
+                            let shared = shared.clone();
+                            let exit_flag = exit_flag.clone();
+                            let stream_error_sender = stream_error_sender.clone();
+                            let config = config.clone();
+
+                            move || {
+                                processor_thread::<M>(
+                                    config,
+                                    to_processor_receiver,
+                                    from_processor_sender,
+                                    stream_channels_receiver,
+                                    taps,
+                                    stream_error_sender,
+                                    shared,
+                                    exit_flag,
+                                );
+                            }
+                        })?
+                };
+
+                (
+                    ProcessorLocation::Thread(stream_channels_sender),
+                    Some(processor_thread),
+                )
+            }
+            ProcessingMode::Callback => (
+                ProcessorLocation::Callback(Box::new(CallbackProcessorState::new(
+                    M::create_processor,
+                    processor_sample_rate,
+                    config.channels,
+                    to_processor_receiver,
+                    from_processor_sender,
+                    taps,
+                    stream_error_sender.clone(),
+                    shared.clone(),
+                ))),
+                None,
+            ),
+        };
+
         // Start the stream manager thread
         let stream_manager_thread = {
             thread::Builder::new()
                 .name("stream_manager".to_string())
                 .spawn({
+                    let shared = shared.clone();
                     let exit_flag = exit_flag.clone();
-                    let stream_error_sender = stream_error_sender.clone();
 
                     move || {
                         stream_manager_thread(
-                            processor_sample_rate,
-                            stream_channels_sender,
+                            device_settings,
+                            config,
+                            processor_location,
                             stream_error_sender,
                             stream_error_receiver,
-                            exit_flag,
-                        );
-                    }
-                })?
-        };
-
-        // Start the processor thread
-        let processor_thread = {
-            thread::Builder::new()
-                .name("audio_processor".to_string())
-                .spawn({
-                    let exit_flag = exit_flag.clone();
-                    move || {
-                        processor_thread::<M>(
-                            processor_sample_rate,
-                            to_processor_receiver,
-                            from_processor_sender,
-                            stream_channels_receiver,
-                            stream_error_sender,
+                            shared,
                             exit_flag,
                         );
                     }
@@ -113,112 +531,263 @@ impl<M: AudioModule> AudioStream<M> {
             to_processor: to_processor_sender,
             from_processor: from_processor_receiver,
             sample_rate: processor_sample_rate,
+            shared,
+            events: event_receiver,
+            recorder: Mutex::new(recorder),
+            input_source,
+            #[cfg(all(target_os = "linux", feature = "jack"))]
+            jack_client: None,
             exit_flag,
-            processor_thread: Some(processor_thread),
+            processor_thread,
             stream_manager_thread: Some(stream_manager_thread),
         })
     }
+}
 
-    pub fn to_processor(&self) -> ToProcessorSender {
-        self.to_processor.clone()
+/// Checks that the stream's settings can be honored by a JACK client, see [AudioStreamBuilder::jack]
+#[cfg(all(target_os = "linux", feature = "jack"))]
+fn check_jack_config(
+    config: &StreamConfig,
+    device_settings: &DeviceSettings,
+    server_sample_rate: usize,
+) -> Result<()> {
+    let reason = if config.mode != ProcessingMode::Callback {
+        Some("JACK clients run the processor in the process callback, use ProcessingMode::Callback")
+    } else if matches!(config.sample_rate, ProcessorSampleRate::Fixed(sample_rate)
+        if sample_rate != server_sample_rate)
+    {
+        Some("JACK clients run at the server's sample rate, use ProcessorSampleRate::FollowOutput")
+    } else if config.routing != ChannelRouting::default() {
+        Some("JACK clients don't support channel routing, ports are connected in a patchbay")
+    } else if device_settings.input != DeviceSelection::Default
+        || device_settings.output != DeviceSelection::Default
+    {
+        Some("JACK clients don't support device selection, ports are connected in a patchbay")
+    } else if device_settings.policy != DevicePolicy::default() {
+        Some("JACK clients don't support device policies")
+    } else {
+        None
+    };
+
+    match reason {
+        Some(reason) => Err(Error::InvalidStreamConfig { reason }),
+        None => Ok(()),
     }
+}
 
-    pub fn from_processor(&self) -> FromProcessorReceiver<M::Processor> {
-        self.from_processor.clone()
+/// The processor's settings, resolved from the [StreamConfig] when the stream is built
+#[derive(Clone)]
+struct ProcessorConfig {
+    sample_rate: usize,
+    frames_per_update: usize,
+    channels: usize,
+    output_only: bool,
+    resampler: ResamplerConfig,
+    routing: ChannelRouting,
+}
+
+impl ProcessorConfig {
+    fn update_duration(&self) -> f64 {
+        self.frames_per_update as f64 / self.sample_rate as f64
     }
 
-    pub fn sample_rate(&self) -> usize {
-        self.sample_rate
+    /// Returns the config for a resampling channel, along with the channel's stats
+    ///
+    /// `default_latency` is used when a latency hasn't been set in the [ResamplerConfig].
+    fn resampling_channel_config(
+        &self,
+        default_latency: f64,
+    ) -> (ResamplingChannelConfig, ResamplerStats) {
+        let latency_seconds = self
+            .resampler
+            .latency
+            .map_or(default_latency, |latency| latency.as_secs_f64());
+        let capacity_seconds = match self.resampler.capacity {
+            Some(capacity) => capacity.as_secs_f64().max(latency_seconds),
+            None => latency_seconds * 16.0,
+        };
+        let quality = match self.resampler.quality {
+            ResamplerQuality::Low => ResampleQuality::Low,
+            ResamplerQuality::High => ResampleQuality::High,
+        };
+
+        (
+            ResamplingChannelConfig {
+                latency_seconds,
+                capacity_seconds,
+                quality,
+                ..Default::default()
+            },
+            ResamplerStats {
+                quality: self.resampler.quality,
+                target_latency: Duration::from_secs_f64(latency_seconds),
+                capacity: Duration::from_secs_f64(capacity_seconds),
+                measured_latency: Duration::ZERO,
+            },
+        )
     }
 }
 
-impl<M: AudioModule> Drop for AudioStream<M> {
-    fn drop(&mut self) {
-        self.exit_flag.store(true, Ordering::Relaxed);
+/// State that moves along with the processor, used to tap and replace its audio
+struct ProcessorTaps {
+    recording: RecordingTap,
+    input_source: InputSourcePlayer,
+}
 
-        self.processor_thread.take().map(JoinHandle::join);
-        self.stream_manager_thread.take().map(JoinHandle::join);
+impl ProcessorTaps {
+    fn detached(&self) -> Self {
+        Self {
+            recording: self.recording.detached(),
+            input_source: self.input_source.detached(),
+        }
     }
 }
 
 struct Streams {
-    input: Stream,
-    input_config: SupportedStreamConfig,
+    // The input stream isn't used for output-only streams
+    input: Option<Stream>,
+    input_config: Option<SupportedStreamConfig>,
 
     output: Stream,
     output_config: SupportedStreamConfig,
 }
 
 impl Streams {
-    fn config_change_detected(&self) -> bool {
-        let host = cpal::default_host();
-
-        let new_input_config = host
-            .default_input_device()
-            .and_then(|device| device.default_input_config().ok());
-        let new_output_config = host
-            .default_output_device()
+    fn config_change_detected(&self, device_settings: &DeviceSettings) -> bool {
+        let Ok(host) = device_settings.host() else {
+            return true;
+        };
+
+        let input_changed = self.input_config.as_ref().is_some_and(|input_config| {
+            device_settings
+                .input_device(&host)
+                .ok()
+                .and_then(|device| device.default_input_config().ok())
+                .is_none_or(|config| config != *input_config)
+        });
+        let new_output_config = device_settings
+            .output_device(&host)
+            .ok()
             .and_then(|device| device.default_output_config().ok());
 
-        new_input_config.is_none_or(|config| config != self.input_config)
-            || new_output_config.is_none_or(|config| config != self.output_config)
+        input_changed || new_output_config.is_none_or(|config| config != self.output_config)
     }
 }
 
 struct StreamChannels {
-    from_input: ResamplingCons<f32>,
-    to_output: ResamplingProd<f32, CHANNELS>,
+    from_input: Option<ResamplingCons<f32>>,
+    to_output: ResamplingProd<f32, MAX_CHANNELS>,
+    // The target latency of the output channel, used to pace output-only streams
+    output_latency_seconds: f64,
 }
 
-fn stream_manager_thread(
-    processor_sample_rate: usize,
-    stream_channels_sender: Sender<Option<StreamChannels>>,
+/// Where the processor runs, see [ProcessingMode]
+enum ProcessorLocation<P: AudioProcessor> {
+    /// The processor thread receives the channels for each new set of streams
+    Thread(Sender<Option<StreamChannels>>),
+    /// The processor gets moved into each new output stream
+    Callback(Box<CallbackProcessorState<P>>),
+}
+
+fn stream_manager_thread<P: AudioProcessor>(
+    mut device_settings: DeviceSettings,
+    processor_config: ProcessorConfig,
+    mut processor_location: ProcessorLocation<P>,
     stream_error_sender: Sender<Error>,
     stream_error_receiver: Receiver<Error>,
+    shared: Arc<SharedStreamState>,
     exit_flag: Arc<AtomicBool>,
 ) {
     let update_interval = Duration::from_millis(50);
     let config_check_interval = Duration::from_secs(1);
     let mut streams: Option<Streams> = None;
+    let mut device_watcher = DeviceWatcher::default();
+    // Set once the streams have been started successfully
+    let mut started_once = false;
+    // Set when the streams have been stopped by DevicePolicy::Stop
+    let mut stopped = false;
+    // Set while the streams are failing to start, so that each failure is only reported once
+    let mut failing = false;
 
     let mut next_config_check = Instant::now();
 
     while !exit_flag.load(Ordering::Relaxed) {
         while let Ok(error) = stream_error_receiver.try_recv() {
             error!("{error}");
+            shared.report_error(&error);
         }
 
         let now = Instant::now();
         if now > next_config_check {
             next_config_check = now + config_check_interval;
 
-            if streams.as_ref().is_none_or(Streams::config_change_detected) {
+            if let Ok(host) = device_settings.host() {
+                for event in device_watcher.check(&host) {
+                    info!("Device event: {event:?}");
+                    shared.send_event(event);
+                }
+            }
+
+            if !stopped
+                && streams
+                    .as_ref()
+                    .is_none_or(|streams| streams.config_change_detected(&device_settings))
+            {
                 debug!("Stream config change detected");
 
-                if stream_channels_sender.send(None).is_err() {
+                if let ProcessorLocation::Thread(stream_channels_sender) = &processor_location
+                    && stream_channels_sender.send(None).is_err()
+                {
                     warn!("Failed to send channels reset to processor thread, exiting");
                     break;
                 }
 
                 if let Some(streams) = streams.take() {
-                    if let Err(error) = streams.input.pause() {
+                    if let Some(Err(error)) = streams.input.as_ref().map(Stream::pause) {
                         warn!("Failed to pause input stream: {error}");
                     }
                     if let Err(error) = streams.output.pause() {
                         warn!("Failed to pause output stream: {error}");
                     }
+                    shared.streams_stopped();
+                }
+
+                if started_once && device_settings.policy == DevicePolicy::Stop {
+                    info!("Stream devices changed, stopping");
+                    stopped = true;
+                    sleep(update_interval);
+                    continue;
                 }
 
-                match initialize_streams(processor_sample_rate, stream_error_sender.clone()) {
-                    Ok((new_streams, stream_channels)) => {
+                match initialize_streams(
+                    &device_settings,
+                    &processor_config,
+                    &mut processor_location,
+                    stream_error_sender.clone(),
+                ) {
+                    Ok((new_streams, started_streams)) => {
                         streams = Some(new_streams);
-                        if stream_channels_sender.send(Some(stream_channels)).is_err() {
-                            warn!("Failed to send stream channels to processor thread, exiting");
-                            break;
+                        failing = false;
+
+                        // Pin the devices that were used so that the streams stick to them
+                        if device_settings.policy == DevicePolicy::StickToDevice {
+                            if let Some(input) = &started_streams.input {
+                                device_settings.input =
+                                    DeviceSelection::Name(input.device_name.clone());
+                            }
+                            device_settings.output =
+                                DeviceSelection::Name(started_streams.output.device_name.clone());
                         }
+
+                        shared.streams_started(started_streams);
+                        started_once = true;
                     }
                     Err(error) => {
-                        error!("Failed to initialize streams: {error}");
+                        if !failing {
+                            error!("Failed to initialize streams: {error}");
+                            shared.stream_failed(&error);
+                            failing = true;
+                        }
                     }
                 }
             }
@@ -230,28 +799,40 @@ fn stream_manager_thread(
     info!("Stream manager thread exiting");
 }
 
+#[allow(clippy::too_many_arguments)]
 fn processor_thread<M: AudioModule>(
-    sample_rate: usize,
+    config: ProcessorConfig,
     to_processor: ToProcessorReceiver,
     from_processor: FromProcessorSender<M::Processor>,
     from_monitor_thread: Receiver<Option<StreamChannels>>,
+    taps: ProcessorTaps,
     stream_error_sender: Sender<Error>,
+    shared: Arc<SharedStreamState>,
     exit_flag: Arc<AtomicBool>,
 ) {
-    if let Err(error) =
-        promote_current_thread_to_real_time(FRAMES_PER_UPDATE as u32, sample_rate as u32)
-    {
+    if let Err(error) = promote_current_thread_to_real_time(
+        config.frames_per_update as u32,
+        config.sample_rate as u32,
+    ) {
         error!("Failed to promote processor thread priority: {error}");
     }
 
-    let mut processor = M::create_processor(sample_rate);
+    let mut runner = ProcessorRunner::new(
+        M::create_processor(config.sample_rate),
+        taps,
+        config.channels,
+        config.sample_rate,
+        to_processor,
+        from_processor,
+        stream_error_sender.clone(),
+        shared.clone(),
+    );
     let mut stream_channels = None;
 
-    let frames_per_update = FRAMES_PER_UPDATE;
-    let mut buffer = vec![0.0f32; frames_per_update * CHANNELS];
+    let mut buffer = vec![0.0f32; config.frames_per_update * config.channels];
 
     let mut update_time = Instant::now();
-    let update_interval = Duration::from_secs_f64(frames_per_update as f64 / sample_rate as f64);
+    let update_interval = Duration::from_secs_f64(config.update_duration());
 
     while !exit_flag.load(Ordering::Relaxed) {
         while let Ok(new_channels) = from_monitor_thread.try_recv() {
@@ -259,54 +840,61 @@ fn processor_thread<M: AudioModule>(
         }
 
         if let Some(channels) = &mut stream_channels {
-            let input_received = match channels.from_input.read_interleaved(&mut buffer) {
-                ReadStatus::Ok => true,
-                ReadStatus::InputNotReady => false,
-                ReadStatus::UnderflowOccurred { num_frames_read } => {
-                    stream_error_sender
-                        .try_send(Error::StreamReadUnderflow {
-                            name: "input -> processor",
-                            frames: num_frames_read,
-                        })
-                        .ok();
-                    true
-                }
-                ReadStatus::OverflowCorrected {
-                    num_frames_discarded,
-                } => {
-                    stream_error_sender
-                        .try_send(Error::StreamReadOverflow {
-                            name: "input -> processor",
-                            frames: num_frames_discarded,
-                        })
-                        .ok();
-                    true
+            match &mut channels.from_input {
+                Some(from_input) => {
+                    let input_received = match from_input.read_interleaved(&mut buffer) {
+                        ReadStatus::Ok => true,
+                        ReadStatus::InputNotReady => false,
+                        ReadStatus::UnderflowOccurred { num_frames_read } => {
+                            stream_error_sender
+                                .try_send(Error::StreamReadUnderflow {
+                                    channel: StreamChannel::InputToProcessor,
+                                    frames: num_frames_read,
+                                })
+                                .ok();
+                            true
+                        }
+                        ReadStatus::OverflowCorrected {
+                            num_frames_discarded,
+                        } => {
+                            stream_error_sender
+                                .try_send(Error::StreamReadOverflow {
+                                    channel: StreamChannel::InputToProcessor,
+                                    frames: num_frames_discarded,
+                                })
+                                .ok();
+                            true
+                        }
+                    };
+                    shared.record_resampler_latency(
+                        StreamChannel::InputToProcessor,
+                        from_input.occupied_seconds(),
+                    );
+
+                    if runner.process(&mut buffer, input_received) {
+                        push_to_output(channels, &buffer, &stream_error_sender, &shared);
+                    }
                 }
-            };
+                None => {
+                    // Output-only streams are paced by the output device rather than the timer,
+                    // with silent buffers processed until the output channel reaches its target
+                    // latency. This prevents drift between the system clock and the device's
+                    // clock from causing underflows or overflows.
+                    let max_blocks = (channels.output_latency_seconds
+                        / update_interval.as_secs_f64())
+                    .ceil() as usize
+                        + 1;
+                    for _ in 0..max_blocks {
+                        if channels.to_output.occupied_seconds() >= channels.output_latency_seconds
+                        {
+                            break;
+                        }
 
-            if input_received {
-                processor.process_buffer(&mut buffer, CHANNELS, &to_processor, &from_processor);
-
-                match channels.to_output.push_interleaved(&buffer) {
-                    PushStatus::Ok => {}
-                    PushStatus::OutputNotReady => {}
-                    PushStatus::OverflowOccurred { num_frames_pushed } => {
-                        stream_error_sender
-                            .try_send(Error::StreamPushOverflow {
-                                name: "processor -> output",
-                                frames: num_frames_pushed,
-                            })
-                            .ok();
-                    }
-                    PushStatus::UnderflowCorrected {
-                        num_zero_frames_pushed,
-                    } => {
-                        stream_error_sender
-                            .try_send(Error::StreamPushOverflow {
-                                name: "processor -> output",
-                                frames: num_zero_frames_pushed,
-                            })
-                            .ok();
+                        buffer.fill(0.0);
+                        runner.process(&mut buffer, true);
+                        if !push_to_output(channels, &buffer, &stream_error_sender, &shared) {
+                            break;
+                        }
                     }
                 }
             }
@@ -321,114 +909,214 @@ fn processor_thread<M: AudioModule>(
     info!("Processor thread exiting");
 }
 
-fn initialize_streams(
-    processor_sample_rate: usize,
-    stream_error_sender: Sender<Error>,
-) -> Result<(Streams, StreamChannels)> {
-    let host = cpal::default_host();
-
-    let Some(input_device) = host.default_input_device() else {
-        return Err(Error::DefaultDeviceUnavailable { stream: "input" });
-    };
-    let input_config = input_device.default_input_config()?;
-    debug!("default input config: {input_config:?}");
-    let input_channels = input_config.channels() as usize;
-    let input_sample_rate = input_config.sample_rate().0 as usize;
-    let input_resampling_factor = (input_sample_rate as f64) / (processor_sample_rate as f64);
-    let input_frames_per_update =
-        ((FRAMES_PER_UPDATE as f64) * input_resampling_factor).ceil() as u32;
-    let input_frames_per_update = match input_config.buffer_size() {
-        SupportedBufferSize::Range { min, max } => input_frames_per_update.clamp(*min, *max),
-        SupportedBufferSize::Unknown => input_frames_per_update,
+/// Pushes a processed buffer to the output channel
+///
+/// Returns false if the output isn't ready to receive audio yet.
+fn push_to_output(
+    channels: &mut StreamChannels,
+    buffer: &[f32],
+    stream_error_sender: &Sender<Error>,
+    shared: &SharedStreamState,
+) -> bool {
+    let output_ready = match channels.to_output.push_interleaved(buffer) {
+        PushStatus::Ok => true,
+        PushStatus::OutputNotReady => false,
+        PushStatus::OverflowOccurred { num_frames_pushed } => {
+            stream_error_sender
+                .try_send(Error::StreamPushOverflow {
+                    channel: StreamChannel::ProcessorToOutput,
+                    frames: num_frames_pushed,
+                })
+                .ok();
+            true
+        }
+        PushStatus::UnderflowCorrected {
+            num_zero_frames_pushed,
+        } => {
+            stream_error_sender
+                .try_send(Error::StreamPushUnderflow {
+                    channel: StreamChannel::ProcessorToOutput,
+                    frames: num_zero_frames_pushed,
+                })
+                .ok();
+            true
+        }
     };
+    shared.record_resampler_latency(
+        StreamChannel::ProcessorToOutput,
+        channels.to_output.occupied_seconds(),
+    );
+    output_ready
+}
 
-    let Some(output_device) = host.default_output_device() else {
-        return Err(Error::DefaultDeviceUnavailable { stream: "output" });
-    };
+/// Initializes the input and output streams
+///
+/// The streams are returned along with the details that are reported in the stream's stats.
+/// For output-only streams the latency from the processor to the output device is reported.
+fn initialize_streams<P: AudioProcessor>(
+    device_settings: &DeviceSettings,
+    processor_config: &ProcessorConfig,
+    processor_location: &mut ProcessorLocation<P>,
+    stream_error_sender: Sender<Error>,
+) -> Result<(Streams, StartedStreams)> {
+    let host = device_settings.host()?;
+    let processor_sample_rate = processor_config.sample_rate;
+    let processor_frames_per_update = processor_config.frames_per_update as f64;
+    let processor_update_duration = processor_config.update_duration();
+    let processor_channels = processor_config.channels;
+
+    let output_device = device_settings.output_device(&host)?;
+    let output_device_name = output_device.name().unwrap_or_default();
     let output_config = output_device.default_output_config()?;
-    debug!("default output config: {output_config:?}");
+    debug!("output config: {output_config:?}");
     let output_channels = output_config.channels() as usize;
-    let output_sample_rate = output_config.sample_rate().0 as usize;
+    let output_sample_rate = match processor_location {
+        ProcessorLocation::Thread(_) => output_config.sample_rate().0 as usize,
+        ProcessorLocation::Callback(_) => {
+            // The output stream runs at the processor's sample rate when the processor is run in
+            // the output callback.
+            let sample_rate = processor_sample_rate as u32;
+            if !output_device.supported_output_configs()?.any(|config| {
+                config.channels() == output_config.channels()
+                    && (config.min_sample_rate().0..=config.max_sample_rate().0)
+                        .contains(&sample_rate)
+            }) {
+                return Err(Error::UnsupportedOutputSampleRate {
+                    device_name: output_device_name,
+                    sample_rate: processor_sample_rate,
+                });
+            }
+            processor_sample_rate
+        }
+    };
     let output_resampling_factor = (output_sample_rate as f64) / (processor_sample_rate as f64);
     let output_frames_per_update =
-        ((FRAMES_PER_UPDATE as f64) * output_resampling_factor).ceil() as u32;
+        (processor_frames_per_update * output_resampling_factor).ceil() as u32;
     let output_frames_per_update = match output_config.buffer_size() {
         SupportedBufferSize::Range { min, max } => output_frames_per_update.clamp(*min, *max),
         SupportedBufferSize::Unknown => output_frames_per_update,
     };
-
-    let input_update_duration = (input_frames_per_update as f64) / (input_sample_rate as f64);
-    let processor_update_duration = (FRAMES_PER_UPDATE as f64) / (processor_sample_rate as f64);
     let output_update_duration = (output_frames_per_update as f64) / (output_sample_rate as f64);
     debug!(
-        "input frames per update: {input_frames_per_update}, \
-         output: {output_frames_per_update}"
-    );
-    debug!(
-        "input update duration: {input_update_duration}, \
-         output: {output_update_duration}, \
-         processor: {processor_update_duration}"
+        "output frames per update: {output_frames_per_update}, \
+         output update duration: {output_update_duration}, \
+         processor update duration: {processor_update_duration}"
     );
 
-    // Set up the input -> processor channel
-    let latency_seconds = input_update_duration.max(processor_update_duration) * 8.0;
-    let capacity_seconds = latency_seconds * 16.0;
-    let (input_to_processor_sender, input_to_processor_receiver) =
-        resampling_channel::<f32, CHANNELS>(
-            NonZeroUsize::new(CHANNELS).unwrap(),
-            input_sample_rate as u32,
-            processor_sample_rate as u32,
-            ResamplingChannelConfig {
-                latency_seconds,
-                capacity_seconds,
-                quality: ResampleQuality::Low,
-                ..Default::default()
-            },
-        );
-
-    // Set up the processor -> output channel
-    let latency_seconds = processor_update_duration.max(output_update_duration) * 8.0;
-    let capacity_seconds = latency_seconds * 16.0;
-    let (processor_to_output_sender, processor_to_output_receiver) =
-        resampling_channel::<f32, CHANNELS>(
-            NonZeroUsize::new(CHANNELS).unwrap(),
-            processor_sample_rate as u32,
-            output_sample_rate as u32,
-            ResamplingChannelConfig {
-                latency_seconds,
-                capacity_seconds,
-                quality: ResampleQuality::Low,
-                ..Default::default()
-            },
-        );
+    let input = if processor_config.output_only {
+        None
+    } else {
+        Some(initialize_input(
+            device_settings,
+            &host,
+            processor_config,
+            processor_location,
+            output_update_duration,
+            stream_error_sender.clone(),
+        )?)
+    };
 
-    let input_stream = initialize_input_stream(
-        input_device,
-        input_channels,
-        input_sample_rate,
-        input_frames_per_update,
-        stream_error_sender.clone(),
-        input_to_processor_sender,
-    )?;
+    let input_device_config = input.as_ref().map(|input| input.device_config.clone());
+    let input_resampler = input.as_ref().map(|input| input.resampler);
+    let input_latency = input.as_ref().map_or(0.0, |input| input.latency);
+    let (input_stream, input_config, from_input) = match input {
+        Some(input) => (
+            Some(input.stream),
+            Some(input.config),
+            Some(input.from_input),
+        ),
+        None => (None, None, None),
+    };
 
-    let output_stream = initialize_output_stream(
-        output_device,
-        output_channels,
-        output_sample_rate,
-        output_frames_per_update,
-        stream_error_sender,
-        processor_to_output_receiver,
-    )?;
+    // The output stream is returned along with the latency that's added by the processing,
+    // and the output resampler's latency
+    let (output_stream, processing_latency, output_resampler) = match processor_location {
+        ProcessorLocation::Thread(stream_channels_sender) => {
+            // Set up the processor -> output channel
+            let (channel_config, resampler_stats) = processor_config.resampling_channel_config(
+                processor_update_duration.max(output_update_duration) * 8.0,
+            );
+            let latency_seconds = channel_config.latency_seconds;
+            let (processor_to_output_sender, processor_to_output_receiver) =
+                resampling_channel::<f32, MAX_CHANNELS>(
+                    NonZeroUsize::new(processor_channels).unwrap(),
+                    processor_sample_rate as u32,
+                    output_sample_rate as u32,
+                    channel_config,
+                );
+
+            let output_stream = initialize_output_stream(
+                output_device,
+                output_channels,
+                OutputMap::new(
+                    &processor_config.routing,
+                    output_channels,
+                    processor_channels,
+                )?,
+                output_sample_rate,
+                output_frames_per_update,
+                stream_error_sender,
+                processor_to_output_receiver,
+            )?;
+
+            stream_channels_sender
+                .send(Some(StreamChannels {
+                    from_input,
+                    to_output: processor_to_output_sender,
+                    output_latency_seconds: latency_seconds,
+                }))
+                .map_err(|_| Error::ProcessorChannelSendFailed)?;
+
+            (
+                output_stream,
+                processor_update_duration + latency_seconds,
+                Some(resampler_stats),
+            )
+        }
+        ProcessorLocation::Callback(state) => {
+            let output_stream = initialize_callback_output_stream(
+                output_device,
+                output_channels,
+                processor_config,
+                output_frames_per_update,
+                state,
+                stream_error_sender,
+                from_input,
+            )?;
+
+            (output_stream, 0.0, None)
+        }
+    };
 
-    input_stream.play()?;
+    if let Some(input_stream) = &input_stream {
+        input_stream.play()?;
+    }
     output_stream.play()?;
 
+    let latency =
+        Duration::from_secs_f64(input_latency + processing_latency + output_update_duration);
+
+    let input_sample_rate = input_device_config
+        .as_ref()
+        .map_or("none".to_string(), |config| config.sample_rate.to_string());
+    let resampler_latency = |resampler: Option<ResamplerStats>| {
+        resampler.map_or("none".to_string(), |resampler| {
+            format!("{:?}", resampler.target_latency)
+        })
+    };
     info!(
         "\
 Audio stream started:
   input sample rate: {input_sample_rate}
   processor sample rate: {processor_sample_rate}
-  output sample rate: {output_sample_rate}"
+  output sample rate: {output_sample_rate}
+  resampler quality: {:?}
+  input resampler latency: {}
+  output resampler latency: {}
+  estimated latency: {latency:?}",
+        processor_config.resampler.quality,
+        resampler_latency(input_resampler),
+        resampler_latency(output_resampler),
     );
 
     Ok((
@@ -438,28 +1126,130 @@ Audio stream started:
             output: output_stream,
             output_config,
         },
-        StreamChannels {
-            from_input: input_to_processor_receiver,
-            to_output: processor_to_output_sender,
+        StartedStreams {
+            input: input_device_config,
+            output: DeviceConfig {
+                device_name: output_device_name,
+                channels: output_channels as u16,
+                sample_rate: output_sample_rate as u32,
+                buffer_size: output_frames_per_update,
+            },
+            input_resampler,
+            output_resampler,
+            latency,
         },
     ))
 }
 
+/// A running input stream, along with the channel that it sends its input to
+struct StreamInput {
+    stream: Stream,
+    config: SupportedStreamConfig,
+    from_input: ResamplingCons<f32>,
+    device_config: DeviceConfig,
+    /// The latency from the input device to the processor, in seconds
+    latency: f64,
+    resampler: ResamplerStats,
+}
+
+/// Initializes the input stream and the input -> processor channel
+fn initialize_input<P: AudioProcessor>(
+    device_settings: &DeviceSettings,
+    host: &Host,
+    processor_config: &ProcessorConfig,
+    processor_location: &ProcessorLocation<P>,
+    output_update_duration: f64,
+    stream_error_sender: Sender<Error>,
+) -> Result<StreamInput> {
+    let processor_sample_rate = processor_config.sample_rate;
+    let processor_channels = processor_config.channels;
+
+    let input_device = device_settings.input_device(host)?;
+    let input_device_name = input_device.name().unwrap_or_default();
+    let input_config = input_device.default_input_config()?;
+    debug!("input config: {input_config:?}");
+
+    let input_channels = input_config.channels() as usize;
+    let input_sample_rate = input_config.sample_rate().0 as usize;
+    let input_resampling_factor = (input_sample_rate as f64) / (processor_sample_rate as f64);
+    let input_frames_per_update =
+        (processor_config.frames_per_update as f64 * input_resampling_factor).ceil() as u32;
+    let input_frames_per_update = match input_config.buffer_size() {
+        SupportedBufferSize::Range { min, max } => input_frames_per_update.clamp(*min, *max),
+        SupportedBufferSize::Unknown => input_frames_per_update,
+    };
+    let input_update_duration = (input_frames_per_update as f64) / (input_sample_rate as f64);
+    debug!(
+        "input frames per update: {input_frames_per_update}, \
+         input update duration: {input_update_duration}"
+    );
+
+    // Set up the input -> processor channel
+    //
+    // When the processor runs in the output callback, the channel only needs to cover the
+    // difference between the input and output buffer sizes.
+    let default_latency = match processor_location {
+        ProcessorLocation::Thread(_) => {
+            input_update_duration.max(processor_config.update_duration()) * 8.0
+        }
+        ProcessorLocation::Callback(_) => input_update_duration.max(output_update_duration) * 2.0,
+    };
+    let (channel_config, resampler_stats) =
+        processor_config.resampling_channel_config(default_latency);
+    let latency_seconds = channel_config.latency_seconds;
+    let (input_to_processor_sender, input_to_processor_receiver) =
+        resampling_channel::<f32, MAX_CHANNELS>(
+            NonZeroUsize::new(processor_channels).unwrap(),
+            input_sample_rate as u32,
+            processor_sample_rate as u32,
+            channel_config,
+        );
+
+    let stream = initialize_input_stream(
+        input_device,
+        input_channels,
+        InputMap::new(
+            &processor_config.routing,
+            input_channels,
+            processor_channels,
+        )?,
+        input_sample_rate,
+        input_frames_per_update,
+        stream_error_sender,
+        input_to_processor_sender,
+    )?;
+
+    Ok(StreamInput {
+        stream,
+        config: input_config,
+        from_input: input_to_processor_receiver,
+        device_config: DeviceConfig {
+            device_name: input_device_name,
+            channels: input_channels as u16,
+            sample_rate: input_sample_rate as u32,
+            buffer_size: input_frames_per_update,
+        },
+        latency: input_update_duration + latency_seconds,
+        resampler: resampler_stats,
+    })
+}
+
 fn initialize_input_stream(
     device: Device,
     channels: usize,
+    input_map: InputMap,
     sample_rate: usize,
     frames_per_update: u32,
     error_sender: Sender<Error>,
-    mut input_to_processor: ResamplingProd<f32, 2>,
+    mut input_to_processor: ResamplingProd<f32, MAX_CHANNELS>,
 ) -> Result<Stream> {
     let mut send_to_processor_fn =
-        move |buffer: &[f32]| match input_to_processor.push_interleaved(&buffer) {
+        move |buffer: &[f32]| match input_to_processor.push_interleaved(buffer) {
             PushStatus::Ok | PushStatus::OutputNotReady => {}
             PushStatus::OverflowOccurred { num_frames_pushed } => {
                 error_sender
                     .try_send(Error::StreamPushOverflow {
-                        name: "input -> processor",
+                        channel: StreamChannel::InputToProcessor,
                         frames: num_frames_pushed,
                     })
                     .ok();
@@ -469,70 +1259,45 @@ fn initialize_input_stream(
             } => {
                 error_sender
                     .try_send(Error::StreamPushUnderflow {
-                        name: "input -> processor",
+                        channel: StreamChannel::InputToProcessor,
                         frames: num_zero_frames_pushed,
                     })
                     .ok();
             }
         };
 
-    let config = StreamConfig {
+    let config = DeviceStreamConfig {
         channels: channels as u16,
         sample_rate: SampleRate(sample_rate as u32),
         buffer_size: BufferSize::Fixed(frames_per_update),
     };
     debug!("Setting up input stream with config: {config:?}");
 
-    let mut buffer = vec![0.0; frames_per_update as usize * CHANNELS];
-    let result = match channels {
-        0 => {
-            return Err(Error::DeviceHasNoAvailableChannels {
-                device_name: device.name().unwrap_or_default(),
-                stream: "input",
-            });
-        }
-        1 => device.build_input_stream(
+    if channels == 0 {
+        return Err(Error::DeviceHasNoAvailableChannels {
+            device_name: device.name().unwrap_or_default(),
+            stream: "input",
+        });
+    }
+
+    let processor_channels = input_map.processor_channels();
+    let mut buffer = vec![0.0; frames_per_update as usize * processor_channels];
+    let result = if input_map.is_identity() {
+        device.build_input_stream(
             &config,
-            move |data: &[f32], _info: &InputCallbackInfo| {
-                for (sample, buffer_frame) in data.iter().zip(buffer.chunks_exact_mut(CHANNELS)) {
-                    buffer_frame.fill(*sample);
-                }
-                send_to_processor_fn(&buffer);
-            },
+            move |data: &[f32], _info: &InputCallbackInfo| send_to_processor_fn(data),
             move |err| error!("Error on audio input stream: {}", err),
             None,
-        ),
-        2 => device.build_input_stream(
+        )
+    } else {
+        device.build_input_stream(
             &config,
             move |data: &[f32], _info: &InputCallbackInfo| {
-                for (input_sample, buffer_sample) in data.iter().zip(buffer.iter_mut()) {
-                    *buffer_sample = *input_sample;
-                }
-
-                send_to_processor_fn(&buffer);
+                input_map.apply_in_chunks(data, &mut buffer, &mut send_to_processor_fn)
             },
             move |err| error!("Error on audio input stream: {}", err),
             None,
-        ),
-        _ => device.build_input_stream(
-            &config,
-            move |data: &[f32], _info: &InputCallbackInfo| {
-                for (input_frame, buffer_frame) in data
-                    .chunks_exact(channels)
-                    .zip(buffer.chunks_exact_mut(CHANNELS))
-                {
-                    for (input_sample, buffer_sample) in
-                        input_frame.iter().zip(buffer_frame.iter_mut())
-                    {
-                        *buffer_sample = *input_sample;
-                    }
-                }
-
-                send_to_processor_fn(&buffer);
-            },
-            move |err| error!("Error on audio input stream: {}", err),
-            None,
-        ),
+        )
     }?;
 
     Ok(result)
@@ -541,6 +1306,7 @@ fn initialize_input_stream(
 fn initialize_output_stream(
     device: Device,
     channels: usize,
+    output_map: OutputMap,
     sample_rate: usize,
     frames_per_update: u32,
     error_sender: Sender<Error>,
@@ -553,7 +1319,7 @@ fn initialize_output_stream(
             ReadStatus::UnderflowOccurred { num_frames_read } => {
                 error_sender
                     .try_send(Error::StreamReadUnderflow {
-                        name: "processor -> output",
+                        channel: StreamChannel::ProcessorToOutput,
                         frames: num_frames_read,
                     })
                     .ok();
@@ -563,67 +1329,45 @@ fn initialize_output_stream(
             } => {
                 error_sender
                     .try_send(Error::StreamReadOverflow {
-                        name: "processor -> output",
+                        channel: StreamChannel::ProcessorToOutput,
                         frames: num_frames_discarded,
                     })
                     .ok();
             }
         };
 
-    let config = StreamConfig {
+    let config = DeviceStreamConfig {
         channels: channels as u16,
         sample_rate: SampleRate(sample_rate as u32),
         buffer_size: BufferSize::Fixed(frames_per_update),
     };
     debug!("Setting up output stream with config: {config:?}");
 
-    let mut buffer = vec![0.0; frames_per_update as usize * CHANNELS];
-    let result = match channels {
-        0 => {
-            return Err(Error::DeviceHasNoAvailableChannels {
-                device_name: device.name().unwrap_or_default(),
-                stream: "output",
-            });
-        }
-        1 => device.build_output_stream(
-            &config,
-            move |data: &mut [f32], _info: &OutputCallbackInfo| {
-                read_from_processor_fn(&mut buffer);
+    if channels == 0 {
+        return Err(Error::DeviceHasNoAvailableChannels {
+            device_name: device.name().unwrap_or_default(),
+            stream: "output",
+        });
+    }
 
-                for (output_sample, processed_frame) in
-                    data.iter_mut().zip(buffer.chunks_exact(CHANNELS))
-                {
-                    *output_sample = processed_frame.iter().copied().sum();
-                }
-            },
-            move |err| error!("Error on audio output stream: {}", err),
-            None,
-        ),
-        2 => device.build_output_stream(
+    let processor_channels = output_map.processor_channels();
+    let mut buffer = vec![0.0; frames_per_update as usize * processor_channels];
+    let result = if output_map.is_identity() {
+        device.build_output_stream(
             &config,
             move |data: &mut [f32], _info: &OutputCallbackInfo| read_from_processor_fn(data),
             move |err| error!("Error on audio output stream: {}", err),
             None,
-        ),
-        _ => device.build_output_stream(
+        )
+    } else {
+        device.build_output_stream(
             &config,
             move |data: &mut [f32], _info: &OutputCallbackInfo| {
-                read_from_processor_fn(&mut buffer);
-
-                for (output_frame, processed_frame) in data
-                    .chunks_exact_mut(channels)
-                    .zip(buffer.chunks_exact(CHANNELS))
-                {
-                    for (output_sample, processed_sample) in
-                        output_frame.iter_mut().zip(processed_frame.iter())
-                    {
-                        *output_sample = *processed_sample;
-                    }
-                }
+                output_map.apply_in_chunks(&mut buffer, data, &mut read_from_processor_fn)
             },
             move |err| error!("Error on audio output stream: {}", err),
             None,
-        ),
+        )
     }?;
 
     Ok(result)
@@ -670,7 +1414,6 @@ impl PopMessage<ToProcessor> for ToProcessorReceiver {
 }
 
 /// An implementation of [PushMessage] that sends messages to [FromProcessorReceiver].
-#[derive(Clone)]
 pub struct FromProcessorSender<P: AudioProcessor>(Sender<P::OutputMessage>);
 
 impl<P: AudioProcessor> FromProcessorSender<P> {
@@ -685,6 +1428,12 @@ impl<P: AudioProcessor> PushMessage<P::OutputMessage> for FromProcessorSender<P>
     }
 }
 
+impl<P: AudioProcessor> Clone for FromProcessorSender<P> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
 /// An implementation of [PopMessage] that wraps a crossbeam_channel receiver.
 pub struct FromProcessorReceiver<P: AudioProcessor>(Receiver<P::OutputMessage>);
 
@@ -710,6 +1459,11 @@ impl<P: AudioProcessor> Clone for FromProcessorReceiver<P> {
 pub enum Error {
     #[error("default {stream} audio device is unavailable")]
     DefaultDeviceUnavailable { stream: &'static str },
+    #[error("{stream} audio device not found (device: {device_name})")]
+    DeviceNotFound {
+        device_name: String,
+        stream: &'static str,
+    },
     #[error("no channels available for the {stream} stream (device: {device_name})")]
     DeviceHasNoAvailableChannels {
         device_name: String,
@@ -719,17 +1473,62 @@ pub enum Error {
     FramesMissing { missing_frames: usize },
     #[error("invalid stream buffer size (size: {buffer_size}, max: {max_size})")]
     InvalidStreamBufferSize { buffer_size: u32, max_size: u32 },
+    #[error("invalid stream config: {reason}")]
+    InvalidStreamConfig { reason: &'static str },
+    #[error(
+        "{stream} channel route to channel {channel} is out of range, \
+         the device has {device_channels} channels"
+    )]
+    InvalidChannelRoute {
+        stream: &'static str,
+        channel: usize,
+        device_channels: usize,
+    },
+    #[error("output sample rate {sample_rate}Hz is unsupported (device: {device_name})")]
+    UnsupportedOutputSampleRate {
+        device_name: String,
+        sample_rate: usize,
+    },
     #[error("failed to send channel to processor thread")]
     ProcessorChannelSendFailed,
+    #[error("a recording is already in progress")]
+    RecordingInProgress,
+    #[error("the stream isn't running")]
+    StreamNotRunning,
+    #[error("failed to send command to the recorder")]
+    RecorderCommandSendFailed,
+    #[error("failed to send the input source to the processor")]
+    InputSourceSendFailed,
+    #[error("recording overflow, {frames} frames were dropped")]
+    RecordingOverflow { frames: usize },
+
+    #[error("{channel} push underflow ({frames} frames)")]
+    StreamPushUnderflow {
+        channel: StreamChannel,
+        frames: usize,
+    },
+    #[error("{channel} push overflow ({frames} frames)")]
+    StreamPushOverflow {
+        channel: StreamChannel,
+        frames: usize,
+    },
+    #[error("{channel} read underflow ({frames} frames)")]
+    StreamReadUnderflow {
+        channel: StreamChannel,
+        frames: usize,
+    },
+    #[error("{channel} read overflow ({frames} frames)")]
+    StreamReadOverflow {
+        channel: StreamChannel,
+        frames: usize,
+    },
 
-    #[error("{name} push underflow ({frames} frames)")]
-    StreamPushUnderflow { name: &'static str, frames: usize },
-    #[error("{name} push overflow ({frames} frames)")]
-    StreamPushOverflow { name: &'static str, frames: usize },
-    #[error("{name} read underflow ({frames} frames)")]
-    StreamReadUnderflow { name: &'static str, frames: usize },
-    #[error("{name} read overflow ({frames} frames)")]
-    StreamReadOverflow { name: &'static str, frames: usize },
+    #[cfg(all(target_os = "linux", feature = "jack"))]
+    #[error("JACK server shut down")]
+    JackShutdown,
+    #[cfg(all(target_os = "linux", feature = "jack"))]
+    #[error("JACK xrun")]
+    JackXrun,
 
     #[error(transparent)]
     BuildStream(#[from] cpal::BuildStreamError),
@@ -740,13 +1539,22 @@ pub enum Error {
     #[error(transparent)]
     DeviceNameError(#[from] cpal::DeviceNameError),
     #[error(transparent)]
+    HostUnavailable(#[from] cpal::HostUnavailable),
+    #[error(transparent)]
     PlayStream(#[from] cpal::PlayStreamError),
     #[error(transparent)]
     PauseStream(#[from] cpal::PauseStreamError),
     #[error(transparent)]
+    SupportedStreamConfigs(#[from] cpal::SupportedStreamConfigsError),
+    #[error(transparent)]
     Stream(#[from] cpal::StreamError),
     #[error(transparent)]
     Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Wav(#[from] hound::Error),
+    #[cfg(all(target_os = "linux", feature = "jack"))]
+    #[error(transparent)]
+    Jack(#[from] jack::Error),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,293 @@
+use super::{Error, Result, StreamEvent};
+use cpal::{
+    Device, Host, HostId, SupportedBufferSize, SupportedStreamConfigRange,
+    traits::{DeviceTrait, HostTrait},
+};
+use log::warn;
+
+/// Information about an audio host that's available on the current platform
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostInfo {
+    pub id: HostId,
+    pub name: &'static str,
+    pub is_default: bool,
+}
+
+/// Information about an audio device
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceInfo {
+    pub name: String,
+    /// The device's input capabilities, or `None` if the device has no inputs
+    pub input: Option<DeviceCapabilities>,
+    /// The device's output capabilities, or `None` if the device has no outputs
+    pub output: Option<DeviceCapabilities>,
+    pub is_default_input: bool,
+    pub is_default_output: bool,
+}
+
+/// The configurations supported by an audio device in one direction
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceCapabilities {
+    /// The supported channel counts, in ascending order
+    pub channel_counts: Vec<u16>,
+    /// The supported sample rates, as inclusive `(min, max)` ranges
+    pub sample_rates: Vec<(u32, u32)>,
+    /// The inclusive `(min, max)` range of supported buffer sizes in frames, if known
+    pub buffer_size: Option<(u32, u32)>,
+}
+
+impl DeviceCapabilities {
+    fn from_configs(configs: impl Iterator<Item = SupportedStreamConfigRange>) -> Option<Self> {
+        let mut channel_counts = Vec::new();
+        let mut sample_rates = Vec::new();
+        let mut buffer_size: Option<(u32, u32)> = None;
+
+        for config in configs {
+            channel_counts.push(config.channels());
+            sample_rates.push((config.min_sample_rate().0, config.max_sample_rate().0));
+
+            if let SupportedBufferSize::Range { min, max } = config.buffer_size() {
+                buffer_size = Some(match buffer_size {
+                    Some((current_min, current_max)) => {
+                        (current_min.min(*min), current_max.max(*max))
+                    }
+                    None => (*min, *max),
+                });
+            }
+        }
+
+        if channel_counts.is_empty() {
+            return None;
+        }
+
+        channel_counts.sort();
+        channel_counts.dedup();
+        sample_rates.sort();
+        sample_rates.dedup();
+
+        Some(Self {
+            channel_counts,
+            sample_rates,
+            buffer_size,
+        })
+    }
+}
+
+/// Selects the device that should be used by a stream
+///
+/// Devices are identified by their names, cpal doesn't currently provide stable device ids.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum DeviceSelection {
+    /// The host's default device
+    #[default]
+    Default,
+    /// The device with the given name, as reported in [DeviceInfo::name]
+    Name(String),
+}
+
+/// The direction of a device stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceDirection {
+    Input,
+    Output,
+}
+
+/// What a stream does when the devices that it's using change while it's running
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DevicePolicy {
+    /// The streams are restarted with the selected devices, following the host's default devices
+    /// when [DeviceSelection::Default] is used.
+    #[default]
+    FollowDefault,
+    /// The streams stick to the devices that they were first started with
+    ///
+    /// If a device is removed then the stream waits for it to return before restarting.
+    StickToDevice,
+    /// The streams are stopped and aren't restarted
+    Stop,
+}
+
+/// Returns the audio hosts that are available on the current platform
+pub fn available_hosts() -> Vec<HostInfo> {
+    let default_host = cpal::default_host().id();
+
+    cpal::available_hosts()
+        .into_iter()
+        .map(|id| HostInfo {
+            id,
+            name: id.name(),
+            is_default: id == default_host,
+        })
+        .collect()
+}
+
+/// Returns the devices that are available for the given host
+///
+/// If `host` is `None` then the platform's default host is used.
+pub fn available_devices(host: Option<HostId>) -> Result<Vec<DeviceInfo>> {
+    let host = get_host(host)?;
+
+    let default_input_name = host
+        .default_input_device()
+        .and_then(|device| device.name().ok());
+    let default_output_name = host
+        .default_output_device()
+        .and_then(|device| device.name().ok());
+
+    let mut result = Vec::new();
+    for device in host.devices()? {
+        // Devices without a readable name can't be selected, so they're skipped
+        let name = match device.name() {
+            Ok(name) => name,
+            Err(error) => {
+                warn!("Skipping a device whose name couldn't be read: {error}");
+                continue;
+            }
+        };
+
+        let input = device
+            .supported_input_configs()
+            .ok()
+            .and_then(DeviceCapabilities::from_configs);
+        let output = device
+            .supported_output_configs()
+            .ok()
+            .and_then(DeviceCapabilities::from_configs);
+
+        result.push(DeviceInfo {
+            is_default_input: input.is_some() && default_input_name.as_ref() == Some(&name),
+            is_default_output: output.is_some() && default_output_name.as_ref() == Some(&name),
+            name,
+            input,
+            output,
+        });
+    }
+
+    Ok(result)
+}
+
+/// The host and devices that are used by a stream
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct DeviceSettings {
+    pub host: Option<HostId>,
+    pub input: DeviceSelection,
+    pub output: DeviceSelection,
+    pub policy: DevicePolicy,
+}
+
+impl DeviceSettings {
+    pub fn host(&self) -> Result<Host> {
+        get_host(self.host)
+    }
+
+    pub fn input_device(&self, host: &Host) -> Result<Device> {
+        match &self.input {
+            DeviceSelection::Default => host
+                .default_input_device()
+                .ok_or(Error::DefaultDeviceUnavailable { stream: "input" }),
+            DeviceSelection::Name(name) => find_device(host.input_devices()?, name, "input"),
+        }
+    }
+
+    pub fn output_device(&self, host: &Host) -> Result<Device> {
+        match &self.output {
+            DeviceSelection::Default => host
+                .default_output_device()
+                .ok_or(Error::DefaultDeviceUnavailable { stream: "output" }),
+            DeviceSelection::Name(name) => find_device(host.output_devices()?, name, "output"),
+        }
+    }
+}
+
+/// Watches a host's devices, reporting added and removed devices and changes to the defaults
+#[derive(Default)]
+pub(crate) struct DeviceWatcher {
+    previous: Option<DeviceSnapshot>,
+}
+
+#[derive(Default, PartialEq)]
+struct DeviceSnapshot {
+    devices: Vec<String>,
+    default_input: Option<String>,
+    default_output: Option<String>,
+}
+
+impl DeviceWatcher {
+    /// Checks the host's devices, returning events for any changes since the previous check
+    ///
+    /// No events are returned for the first check.
+    pub fn check(&mut self, host: &Host) -> Vec<StreamEvent> {
+        let mut devices = host
+            .devices()
+            .map(|devices| {
+                devices
+                    .filter_map(|device| device.name().ok())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        devices.sort();
+        devices.dedup();
+
+        let snapshot = DeviceSnapshot {
+            devices,
+            default_input: host
+                .default_input_device()
+                .and_then(|device| device.name().ok()),
+            default_output: host
+                .default_output_device()
+                .and_then(|device| device.name().ok()),
+        };
+
+        let mut events = Vec::new();
+
+        if let Some(previous) = &self.previous
+            && *previous != snapshot
+        {
+            for name in snapshot.devices.iter() {
+                if !previous.devices.contains(name) {
+                    events.push(StreamEvent::DeviceAdded(name.clone()));
+                }
+            }
+            for name in previous.devices.iter() {
+                if !snapshot.devices.contains(name) {
+                    events.push(StreamEvent::DeviceRemoved(name.clone()));
+                }
+            }
+            if snapshot.default_input != previous.default_input {
+                events.push(StreamEvent::DefaultDeviceChanged {
+                    direction: DeviceDirection::Input,
+                    name: snapshot.default_input.clone(),
+                });
+            }
+            if snapshot.default_output != previous.default_output {
+                events.push(StreamEvent::DefaultDeviceChanged {
+                    direction: DeviceDirection::Output,
+                    name: snapshot.default_output.clone(),
+                });
+            }
+        }
+
+        self.previous = Some(snapshot);
+        events
+    }
+}
+
+fn get_host(host: Option<HostId>) -> Result<Host> {
+    match host {
+        Some(id) => Ok(cpal::host_from_id(id)?),
+        None => Ok(cpal::default_host()),
+    }
+}
+
+fn find_device(
+    mut devices: impl Iterator<Item = Device>,
+    name: &str,
+    stream: &'static str,
+) -> Result<Device> {
+    devices
+        .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+        .ok_or_else(|| Error::DeviceNotFound {
+            device_name: name.to_string(),
+            stream,
+        })
+}
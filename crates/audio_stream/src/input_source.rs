@@ -0,0 +1,276 @@
+use super::{Error, Result};
+use crossbeam_channel::{Receiver, Sender, bounded};
+use hound::{SampleFormat, WavReader};
+use log::info;
+use std::{
+    f64::consts::TAU,
+    path::{Path, PathBuf},
+};
+
+/// The period at which test signals repeat, in seconds
+const TEST_SIGNAL_PERIOD: f64 = 1.0;
+/// The duration of a sine sweep, in seconds
+const SWEEP_DURATION: f64 = 5.0;
+/// The frequency range of a sine sweep
+const SWEEP_RANGE: (f64, f64) = (20.0, 20000.0);
+/// The duration of each noise burst, in seconds
+const NOISE_BURST_DURATION: f64 = 0.1;
+/// The amplitude of the sine sweep and noise bursts
+const TEST_SIGNAL_AMPLITUDE: f32 = 0.5;
+/// The number of sources that can be queued for the processor
+const SOURCE_QUEUE_SIZE: usize = 8;
+
+/// Where the audio that's passed to a stream's processor comes from
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum InputSource {
+    /// Audio from the input device, or silence for output-only streams
+    #[default]
+    Device,
+    /// Audio from a WAV file
+    ///
+    /// The file is decoded when the source is set, and is resampled to the processor's sample rate
+    /// if necessary. Mono files are duplicated to all of the processor's channels.
+    File { path: PathBuf, looping: bool },
+    /// An internally generated test signal
+    TestSignal(TestSignal),
+}
+
+/// Test signals that can be used as an [InputSource]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestSignal {
+    /// A single-sample impulse, repeated every second
+    Impulse,
+    /// A logarithmic sine sweep from 20Hz to 20kHz over 5 seconds, repeated
+    SineSweep,
+    /// Bursts of white noise, 100ms long and repeated every second
+    NoiseBursts,
+}
+
+/// The processor-side state of an [InputSource]
+enum Source {
+    Device,
+    File {
+        samples: Vec<f32>,
+        position: usize,
+        looping: bool,
+    },
+    TestSignal {
+        signal: TestSignal,
+        position: usize,
+        phase: f64,
+        noise_state: u32,
+    },
+}
+
+/// Sets the input source for a stream's processor, see `AudioStream::set_input_source`
+///
+/// Sources that have been replaced are returned from the processor so that they get dropped here
+/// rather than on the audio thread, where freeing a decoded file could cause an xrun.
+pub(crate) struct InputSourceControl {
+    sources: Sender<Source>,
+    retired: Receiver<Source>,
+    sample_rate: usize,
+    channels: usize,
+}
+
+impl InputSourceControl {
+    /// Creates the control along with the player that should be owned by the processor
+    pub fn new(sample_rate: usize, channels: usize) -> (Self, InputSourcePlayer) {
+        let (source_sender, source_receiver) = bounded(SOURCE_QUEUE_SIZE);
+        // Each queued source replaces another, and the control drains the retired sources before
+        // queueing a new one, so there's always space to return the current source along with
+        // the queued sources.
+        let (retired_sender, retired_receiver) = bounded(SOURCE_QUEUE_SIZE + 1);
+
+        let control = Self {
+            sources: source_sender,
+            retired: retired_receiver,
+            sample_rate,
+            channels,
+        };
+        let player = InputSourcePlayer {
+            sources: source_receiver,
+            retired: retired_sender,
+            source: Source::Device,
+            sample_rate,
+            channels,
+        };
+
+        (control, player)
+    }
+
+    /// Queues a new source for the processor
+    ///
+    /// An error is returned if the queue is full, e.g. if the processor isn't currently running.
+    pub fn set(&self, source: InputSource) -> Result<()> {
+        // Free the sources that the processor has finished with
+        self.retired.try_iter().for_each(drop);
+
+        let source = match source {
+            InputSource::Device => Source::Device,
+            InputSource::File { path, looping } => {
+                info!("Loading input file {}", path.display());
+                Source::File {
+                    samples: self.load_file(&path)?,
+                    position: 0,
+                    looping,
+                }
+            }
+            InputSource::TestSignal(signal) => Source::TestSignal {
+                signal,
+                position: 0,
+                phase: 0.0,
+                noise_state: 0x9e3779b9,
+            },
+        };
+
+        self.sources
+            .try_send(source)
+            .map_err(|_| Error::InputSourceSendFailed)
+    }
+
+    /// Decodes a WAV file into interleaved samples at the processor's sample rate and channel count
+    fn load_file(&self, path: &Path) -> Result<Vec<f32>> {
+        let mut reader = WavReader::open(path)?;
+        let spec = reader.spec();
+        let file_channels = spec.channels as usize;
+
+        // Integer samples are scaled to the range -1..=1
+        let int_scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+        let file_samples = match spec.sample_format {
+            SampleFormat::Float => reader.samples::<f32>().collect::<hound::Result<Vec<_>>>()?,
+            SampleFormat::Int => reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 * int_scale))
+                .collect::<hound::Result<Vec<_>>>()?,
+        };
+        let file_frames = file_samples.len() / file_channels;
+
+        // Resample using linear interpolation, which is good enough for test material
+        let ratio = spec.sample_rate as f64 / self.sample_rate as f64;
+        let frames = (file_frames as f64 / ratio) as usize;
+        let mut samples = Vec::with_capacity(frames * self.channels);
+
+        for frame in 0..frames {
+            let position = frame as f64 * ratio;
+            let index = position as usize;
+            let fraction = (position - index as f64) as f32;
+            let next_index = (index + 1).min(file_frames - 1);
+
+            for channel in 0..self.channels {
+                // Channels beyond the file's channel count wrap around
+                let file_channel = channel % file_channels;
+                let a = file_samples[index * file_channels + file_channel];
+                let b = file_samples[next_index * file_channels + file_channel];
+                samples.push(a + (b - a) * fraction);
+            }
+        }
+
+        Ok(samples)
+    }
+}
+
+/// Fills the processor's buffers when a source other than the input device is being used
+pub(crate) struct InputSourcePlayer {
+    sources: Receiver<Source>,
+    retired: Sender<Source>,
+    source: Source,
+    sample_rate: usize,
+    channels: usize,
+}
+
+impl InputSourcePlayer {
+    /// Returns a new player that receives sources from the same [InputSourceControl]
+    ///
+    /// This is used when a player has been lost along with its processor, the input device is used
+    /// until a new source is set.
+    pub fn detached(&self) -> Self {
+        Self {
+            sources: self.sources.clone(),
+            retired: self.retired.clone(),
+            source: Source::Device,
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+        }
+    }
+
+    /// Fills the buffer with audio from the current source
+    ///
+    /// Returns false if the input device is the current source, in which case the buffer is left
+    /// untouched.
+    pub fn fill(&mut self, buffer: &mut [f32]) -> bool {
+        while let Ok(source) = self.sources.try_recv() {
+            let previous = std::mem::replace(&mut self.source, source);
+            // The previous source is dropped by the control, see `InputSourceControl::new`
+            self.retired.try_send(previous).ok();
+        }
+
+        match &mut self.source {
+            Source::Device => return false,
+            Source::File {
+                samples,
+                position,
+                looping,
+            } => {
+                for output in buffer.iter_mut() {
+                    if *position >= samples.len() && *looping && !samples.is_empty() {
+                        *position = 0;
+                    }
+
+                    *output = samples.get(*position).copied().unwrap_or(0.0);
+                    *position += 1;
+                }
+            }
+            Source::TestSignal {
+                signal,
+                position,
+                phase,
+                noise_state,
+            } => {
+                let sample_rate = self.sample_rate as f64;
+                let period_frames = (TEST_SIGNAL_PERIOD * sample_rate) as usize;
+
+                for frame in buffer.chunks_exact_mut(self.channels) {
+                    let value = match signal {
+                        TestSignal::Impulse => {
+                            if *position % period_frames == 0 {
+                                1.0
+                            } else {
+                                0.0
+                            }
+                        }
+                        TestSignal::SineSweep => {
+                            let sweep_frames = (SWEEP_DURATION * sample_rate) as usize;
+                            let t = (*position % sweep_frames) as f64 / sweep_frames as f64;
+                            let (start, end) = SWEEP_RANGE;
+                            let end = end.min(sample_rate / 2.0);
+                            let frequency = start * (end / start).powf(t);
+
+                            let value = phase.sin() as f32 * TEST_SIGNAL_AMPLITUDE;
+                            *phase = (*phase + TAU * frequency / sample_rate) % TAU;
+                            value
+                        }
+                        TestSignal::NoiseBursts => {
+                            let burst_frames = (NOISE_BURST_DURATION * sample_rate) as usize;
+                            if *position % period_frames < burst_frames {
+                                // xorshift32
+                                *noise_state ^= *noise_state << 13;
+                                *noise_state ^= *noise_state >> 17;
+                                *noise_state ^= *noise_state << 5;
+                                let noise = *noise_state as f32 / u32::MAX as f32 * 2.0 - 1.0;
+                                noise * TEST_SIGNAL_AMPLITUDE
+                            } else {
+                                0.0
+                            }
+                        }
+                    };
+
+                    frame.fill(value);
+                    *position += 1;
+                }
+            }
+        }
+
+        true
+    }
+}
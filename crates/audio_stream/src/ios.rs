@@ -2,17 +2,27 @@
 
 use log::{error, info};
 
-pub fn setup_audio_session() {
+/// Sets up the audio session, `record` should be false for output-only streams
+pub fn setup_audio_session(record: bool) {
     use objc2_avf_audio::{
         AVAudioSession, AVAudioSessionCategoryOptions as Options,
-        AVAudioSessionCategoryPlayAndRecord,
+        AVAudioSessionCategoryPlayAndRecord, AVAudioSessionCategoryPlayback,
     };
 
     // SAFETY: Calling into system frameworks requires `unsafe`
     unsafe {
         let session = AVAudioSession::sharedInstance();
-        let category = AVAudioSessionCategoryPlayAndRecord.unwrap();
-        let options = Options::MixWithOthers | Options::AllowBluetoothHFP;
+        let (category, options) = if record {
+            (
+                AVAudioSessionCategoryPlayAndRecord.unwrap(),
+                Options::MixWithOthers | Options::AllowBluetoothHFP,
+            )
+        } else {
+            (
+                AVAudioSessionCategoryPlayback.unwrap(),
+                Options::MixWithOthers,
+            )
+        };
 
         match session.setCategory_withOptions_error(category, options) {
             Ok(_) => match session.setActive_error(true) {
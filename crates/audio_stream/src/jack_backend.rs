@@ -0,0 +1,227 @@
+use super::{
+    DeviceConfig, Error, FromProcessorSender, ProcessorRunner, ProcessorTaps, Result,
+    SharedStreamState, StartedStreams, ToProcessorReceiver,
+};
+use audio_module::AudioProcessor;
+use crossbeam_channel::{Receiver, Sender};
+use jack::{
+    AsyncClient, AudioIn, AudioOut, Client, ClientOptions, ClientStatus, Control,
+    NotificationHandler, Port, ProcessHandler, ProcessScope,
+};
+use log::{info, warn};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+/// A running JACK client with the processor in its process callback
+pub(crate) type JackClient<P> = AsyncClient<JackNotifications, JackProcessor<P>>;
+
+/// A JACK client that has been opened but not yet activated
+pub(crate) struct OpenJackClient {
+    client: Client,
+}
+
+impl OpenJackClient {
+    /// Opens a JACK client with the given name
+    ///
+    /// The JACK server isn't started if it isn't already running.
+    pub fn open(name: &str) -> Result<Self> {
+        let (client, status) = Client::new(name, ClientOptions::NO_START_SERVER)?;
+        if status.contains(ClientStatus::NAME_NOT_UNIQUE) {
+            info!(
+                "JACK client name '{name}' was taken, using '{}'",
+                client.name()
+            );
+        }
+
+        Ok(Self { client })
+    }
+
+    /// The server's sample rate
+    pub fn sample_rate(&self) -> usize {
+        self.client.sample_rate()
+    }
+
+    /// The server's buffer size in frames
+    pub fn buffer_size(&self) -> usize {
+        self.client.buffer_size() as usize
+    }
+
+    /// Registers the client's ports and activates it with the processor in its process callback
+    ///
+    /// Input ports are named `in_1`, `in_2`, etc., and output ports `out_1`, `out_2`, etc.
+    /// The ports aren't connected, connections are left to the user's patchbay.
+    #[allow(clippy::too_many_arguments)]
+    pub fn activate<P: AudioProcessor>(
+        self,
+        processor: P,
+        channels: usize,
+        output_only: bool,
+        to_processor: ToProcessorReceiver,
+        from_processor: FromProcessorSender<P>,
+        taps: ProcessorTaps,
+        error_sender: Sender<Error>,
+        shared: Arc<SharedStreamState>,
+        shutdown_flag: Arc<AtomicBool>,
+    ) -> Result<JackClient<P>> {
+        let client = self.client;
+        let sample_rate = client.sample_rate();
+        let frames_per_update = client.buffer_size() as usize;
+
+        let inputs = if output_only {
+            Vec::new()
+        } else {
+            (1..=channels)
+                .map(|channel| client.register_port(&format!("in_{channel}"), AudioIn))
+                .collect::<std::result::Result<Vec<_>, _>>()?
+        };
+        let outputs = (1..=channels)
+            .map(|channel| client.register_port(&format!("out_{channel}"), AudioOut))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let device_config = DeviceConfig {
+            device_name: client.name().to_string(),
+            channels: channels as u16,
+            sample_rate: sample_rate as u32,
+            buffer_size: frames_per_update as u32,
+        };
+        // The input and output each add a period of latency
+        let period = Duration::from_secs_f64(frames_per_update as f64 / sample_rate as f64);
+        let started = StartedStreams {
+            input: (!output_only).then(|| device_config.clone()),
+            output: device_config,
+            input_resampler: None,
+            output_resampler: None,
+            latency: if output_only { period } else { period * 2 },
+        };
+
+        let process_handler = JackProcessor {
+            runner: ProcessorRunner::new(
+                processor,
+                taps,
+                channels,
+                sample_rate,
+                to_processor,
+                from_processor,
+                error_sender.clone(),
+                shared.clone(),
+            ),
+            inputs,
+            outputs,
+            channels,
+            frames_per_update,
+            buffer: vec![0.0; frames_per_update * channels],
+        };
+        let notification_handler = JackNotifications {
+            error_sender,
+            shutdown_flag,
+        };
+
+        let client = client.activate_async(notification_handler, process_handler)?;
+
+        info!(
+            "JACK client '{}' started at {sample_rate}Hz with a buffer size of {frames_per_update}",
+            client.as_client().name()
+        );
+        shared.streams_started(started);
+
+        Ok(client)
+    }
+}
+
+/// Runs the processor in the JACK process callback
+pub(crate) struct JackProcessor<P: AudioProcessor> {
+    runner: ProcessorRunner<P>,
+    inputs: Vec<Port<AudioIn>>,
+    outputs: Vec<Port<AudioOut>>,
+    channels: usize,
+    frames_per_update: usize,
+    // Interleaved audio for the processor
+    buffer: Vec<f32>,
+}
+
+impl<P: AudioProcessor> ProcessHandler for JackProcessor<P> {
+    fn process(&mut self, _client: &Client, process_scope: &ProcessScope) -> Control {
+        let frames = process_scope.n_frames() as usize;
+
+        // The server's buffer size can change while the client is running, so the callback's
+        // frames are processed in blocks that fit the processor's buffer.
+        let mut offset = 0;
+        while offset < frames {
+            let block_frames = (frames - offset).min(self.frames_per_update);
+            let block = offset..offset + block_frames;
+            let buffer = &mut self.buffer[..block_frames * self.channels];
+
+            if self.inputs.is_empty() {
+                // Output-only streams process silent buffers
+                buffer.fill(0.0);
+            } else {
+                for (channel, input) in self.inputs.iter().enumerate() {
+                    let input = &input.as_slice(process_scope)[block.clone()];
+                    for (frame, sample) in input.iter().enumerate() {
+                        buffer[frame * self.channels + channel] = *sample;
+                    }
+                }
+            }
+
+            self.runner.process(buffer, true);
+
+            for (channel, output) in self.outputs.iter_mut().enumerate() {
+                let output = &mut output.as_mut_slice(process_scope)[block.clone()];
+                for (frame, sample) in output.iter_mut().enumerate() {
+                    *sample = buffer[frame * self.channels + channel];
+                }
+            }
+
+            offset += block_frames;
+        }
+
+        Control::Continue
+    }
+}
+
+/// Passes JACK server notifications to the monitor thread, see [monitor_thread]
+///
+/// The shutdown notification is called in a signal handler context, so it only sets a flag.
+pub(crate) struct JackNotifications {
+    error_sender: Sender<Error>,
+    shutdown_flag: Arc<AtomicBool>,
+}
+
+impl NotificationHandler for JackNotifications {
+    fn shutdown(&mut self, _status: ClientStatus, _reason: &str) {
+        self.shutdown_flag.store(true, Ordering::Release);
+    }
+
+    fn xrun(&mut self, _client: &Client) -> Control {
+        self.error_sender.try_send(Error::JackXrun).ok();
+        Control::Continue
+    }
+}
+
+/// Reports errors from the process callback and server notifications until the stream exits
+pub(crate) fn monitor_thread(
+    errors: Receiver<Error>,
+    shared: Arc<SharedStreamState>,
+    shutdown_flag: Arc<AtomicBool>,
+    exit_flag: Arc<AtomicBool>,
+) {
+    while !exit_flag.load(Ordering::Relaxed) {
+        if shutdown_flag.swap(false, Ordering::Acquire) {
+            warn!("JACK server shut down");
+            shared.report_error(&Error::JackShutdown);
+            shared.streams_stopped();
+        }
+
+        if let Ok(error) = errors.recv_timeout(Duration::from_millis(100)) {
+            warn!("{error}");
+            shared.report_error(&error);
+        }
+    }
+
+    info!("JACK monitor thread exiting");
+}
@@ -1,7 +1,8 @@
 #[cfg_attr(not(target_arch = "wasm32"), path = "cpal.rs")]
 #[cfg_attr(target_arch = "wasm32", path = "wasm.rs")]
 mod audio_stream;
+mod chain;
+mod config;
+mod load;
 
-pub use crate::audio_stream::*;
-
-pub const FRAMES_PER_UPDATE: usize = 128;
+pub use crate::{audio_stream::*, chain::*, config::*, load::*};
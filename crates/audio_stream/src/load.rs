@@ -0,0 +1,135 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// A snapshot of a processor's load, see [LoadMeter]
+///
+/// Loads are percentages of the time available for processing each block, so a load above 100%
+/// means that the block's deadline was missed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessorLoad {
+    /// The load of the most recently processed block
+    pub current: f32,
+    pub min: f32,
+    pub avg: f32,
+    pub max: f32,
+    /// The number of blocks that took longer to process than their deadline
+    pub deadline_misses: u64,
+    /// The number of blocks that have been measured
+    pub blocks: u64,
+}
+
+impl fmt::Display for ProcessorLoad {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.blocks == 0 {
+            return write!(f, "n/a");
+        }
+
+        write!(
+            f,
+            "{:.1}% avg, {:.1}% max, {} missed",
+            self.avg, self.max, self.deadline_misses
+        )
+    }
+}
+
+/// Measures the time taken by a processor relative to its processing deadlines
+///
+/// The meter is updated by the processing thread and can be read from any other thread without
+/// locking. Each value is stored in its own atomic, so a snapshot taken while a block is being
+/// recorded might mix values from consecutive blocks.
+pub struct LoadMeter {
+    // Loads are stored as `f32` bits
+    current: AtomicU32,
+    min: AtomicU32,
+    max: AtomicU32,
+    // The sum of all recorded loads, stored as `f64` bits
+    total: AtomicU64,
+    deadline_misses: AtomicU64,
+    blocks: AtomicU64,
+    // Resets are performed by the recording thread to avoid racing with `record`
+    reset_requested: AtomicBool,
+}
+
+impl LoadMeter {
+    pub fn new() -> Self {
+        Self {
+            current: AtomicU32::new(0),
+            min: AtomicU32::new(0),
+            max: AtomicU32::new(0),
+            total: AtomicU64::new(0),
+            deadline_misses: AtomicU64::new(0),
+            blocks: AtomicU64::new(0),
+            reset_requested: AtomicBool::new(false),
+        }
+    }
+
+    /// Records the time taken to process a block, along with the block's deadline
+    ///
+    /// This should only be called from a single thread at a time.
+    pub fn record(&self, processing_time: Duration, deadline: Duration) {
+        if self.reset_requested.swap(false, Ordering::Relaxed) {
+            self.total.store(0, Ordering::Relaxed);
+            self.deadline_misses.store(0, Ordering::Relaxed);
+            self.blocks.store(0, Ordering::Relaxed);
+        }
+
+        let load = (processing_time.as_secs_f64() / deadline.as_secs_f64() * 100.0) as f32;
+        let blocks = self.blocks.load(Ordering::Relaxed);
+
+        self.current.store(load.to_bits(), Ordering::Relaxed);
+        if blocks == 0 || load < load_from_bits(&self.min) {
+            self.min.store(load.to_bits(), Ordering::Relaxed);
+        }
+        if blocks == 0 || load > load_from_bits(&self.max) {
+            self.max.store(load.to_bits(), Ordering::Relaxed);
+        }
+
+        let total = f64::from_bits(self.total.load(Ordering::Relaxed)) + load as f64;
+        self.total.store(total.to_bits(), Ordering::Relaxed);
+
+        if processing_time > deadline {
+            self.deadline_misses.fetch_add(1, Ordering::Relaxed);
+        }
+        self.blocks.store(blocks + 1, Ordering::Relaxed);
+    }
+
+    /// Returns a snapshot of the measurements that have been recorded since the last reset
+    pub fn snapshot(&self) -> ProcessorLoad {
+        let blocks = self.blocks.load(Ordering::Relaxed);
+        let total = f64::from_bits(self.total.load(Ordering::Relaxed));
+
+        ProcessorLoad {
+            current: load_from_bits(&self.current),
+            min: load_from_bits(&self.min),
+            avg: if blocks > 0 {
+                (total / blocks as f64) as f32
+            } else {
+                0.0
+            },
+            max: load_from_bits(&self.max),
+            deadline_misses: self.deadline_misses.load(Ordering::Relaxed),
+            blocks,
+        }
+    }
+
+    /// Clears the recorded measurements
+    ///
+    /// The measurements are cleared when the next block is recorded.
+    pub fn reset(&self) {
+        self.reset_requested.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Default for LoadMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn load_from_bits(load: &AtomicU32) -> f32 {
+    f32::from_bits(load.load(Ordering::Relaxed))
+}
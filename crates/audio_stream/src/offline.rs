@@ -0,0 +1,485 @@
+use super::{
+    Error, FromProcessorReceiver, FromProcessorSender, MAX_CHANNELS, Result, ToProcessorReceiver,
+    ToProcessorSender,
+};
+use crate::{ProcessorSampleRate, StreamConfig};
+use audio_module::{AudioModule, AudioProcessor, PopMessage, PushMessage, ToProcessor};
+use crossbeam_channel::bounded;
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use log::{info, warn};
+use std::{collections::VecDeque, path::Path};
+
+/// The sample rate that's used by [OfflineStream::new] when the config follows the output's
+/// sample rate
+const DEFAULT_SAMPLE_RATE: usize = 44100;
+
+/// The number of frames that are read from the input file at a time while rendering.
+const FRAMES_PER_READ: usize = 4096;
+
+/// A message that should be delivered to the processor at a specific frame offset.
+#[derive(Debug, Clone)]
+pub struct ScheduledMessage {
+    pub frame: usize,
+    pub message: ToProcessor,
+}
+
+impl ScheduledMessage {
+    pub fn new(frame: usize, message: ToProcessor) -> Self {
+        Self { frame, message }
+    }
+}
+
+/// Runs an audio module's processor without an audio device
+///
+/// Audio is processed on the calling thread, in blocks of up to the config's `frames_per_update`
+/// frames. Blocks are split at the frame offsets of scheduled messages so that parameter changes
+/// are applied at the requested position. Messages from the processor are passed to a callback
+/// after each block, so none are dropped however much audio is processed at once.
+pub struct OfflineStream<M: AudioModule> {
+    processor: M::Processor,
+    to_processor_sender: ToProcessorSender,
+    to_processor_receiver: ToProcessorReceiver,
+    from_processor_sender: FromProcessorSender<M::Processor>,
+    from_processor_receiver: FromProcessorReceiver<M::Processor>,
+    sample_rate: usize,
+    frames_per_update: usize,
+    channels: usize,
+    scheduled_messages: VecDeque<ScheduledMessage>,
+    position: usize,
+}
+
+impl<M: AudioModule> OfflineStream<M> {
+    /// Creates a stream using the given config's sample rate, block size, and channel count
+    ///
+    /// There's no output device to follow, so [ProcessorSampleRate::FollowOutput] uses 44.1kHz.
+    pub fn new(config: StreamConfig) -> Result<Self> {
+        if config.channels == 0 || config.channels > MAX_CHANNELS {
+            return Err(Error::InvalidStreamConfig {
+                reason: "the channel count must be between 1 and MAX_CHANNELS",
+            });
+        }
+        if config.frames_per_update == 0 {
+            return Err(Error::InvalidStreamConfig {
+                reason: "at least one frame must be processed per update",
+            });
+        }
+
+        let sample_rate = match config.sample_rate {
+            ProcessorSampleRate::Fixed(sample_rate) => sample_rate,
+            ProcessorSampleRate::FollowOutput => DEFAULT_SAMPLE_RATE,
+        };
+
+        let channel_capacity = 1024;
+
+        let (sender, receiver) = bounded(channel_capacity);
+        let to_processor_sender = ToProcessorSender::new(sender);
+        let to_processor_receiver = ToProcessorReceiver::new(receiver);
+
+        let (sender, receiver) = bounded(channel_capacity);
+        let from_processor_sender = FromProcessorSender::new(sender);
+        let from_processor_receiver = FromProcessorReceiver::new(receiver);
+
+        Ok(Self {
+            processor: M::create_processor(sample_rate),
+            to_processor_sender,
+            to_processor_receiver,
+            from_processor_sender,
+            from_processor_receiver,
+            sample_rate,
+            frames_per_update: config.frames_per_update,
+            channels: config.channels,
+            scheduled_messages: VecDeque::new(),
+            position: 0,
+        })
+    }
+
+    /// Processes `input_path`, writing the result to `output_path`
+    ///
+    /// The processor runs with the config's block size and channel count, and the output file is
+    /// written as 32-bit float audio with the config's channel count. Input channels are mapped to
+    /// the processor's channels in order, wrapping around when the file has fewer channels, so
+    /// mono files feed all of the processor's channels. Messages from the processor are passed to
+    /// `on_message`.
+    ///
+    /// The processor runs at the input file's sample rate, if the config has a fixed sample rate
+    /// that doesn't match the file's then [Error::InvalidStreamConfig] is returned.
+    pub fn render(
+        input_path: impl AsRef<Path>,
+        output_path: impl AsRef<Path>,
+        config: StreamConfig,
+        messages: impl IntoIterator<Item = ScheduledMessage>,
+        on_message: impl FnMut(<M::Processor as AudioProcessor>::OutputMessage),
+    ) -> Result<()> {
+        let mut reader = WavReader::open(input_path)?;
+        let input_spec = reader.spec();
+        let input_channels = input_spec.channels as usize;
+        let sample_rate = input_spec.sample_rate as usize;
+
+        if matches!(config.sample_rate, ProcessorSampleRate::Fixed(fixed) if fixed != sample_rate) {
+            return Err(Error::InvalidStreamConfig {
+                reason: "offline streams run at the input file's sample rate",
+            });
+        }
+
+        let mut stream = Self::new(StreamConfig {
+            sample_rate: ProcessorSampleRate::Fixed(sample_rate),
+            ..config
+        })?;
+        let channels = stream.channels;
+        for message in messages {
+            stream.schedule(message.frame, message.message);
+        }
+
+        let mut writer = WavWriter::create(
+            output_path,
+            WavSpec {
+                channels: channels as u16,
+                sample_rate: input_spec.sample_rate,
+                bits_per_sample: 32,
+                sample_format: SampleFormat::Float,
+            },
+        )?;
+
+        // Integer samples are scaled to the range -1..=1
+        let int_scale = 1.0 / (1u64 << (input_spec.bits_per_sample - 1)) as f32;
+        let mut samples: Box<dyn Iterator<Item = hound::Result<f32>>> =
+            match input_spec.sample_format {
+                SampleFormat::Float => Box::new(reader.samples::<f32>()),
+                SampleFormat::Int => Box::new(
+                    reader
+                        .samples::<i32>()
+                        .map(move |sample| sample.map(|sample| sample as f32 * int_scale)),
+                ),
+            };
+
+        let mut input_frame = vec![0.0; input_channels];
+        let mut buffer = Vec::with_capacity(FRAMES_PER_READ * channels);
+        let mut on_message = on_message;
+
+        loop {
+            buffer.clear();
+
+            'read: while buffer.len() < FRAMES_PER_READ * channels {
+                for input_sample in input_frame.iter_mut() {
+                    match samples.next() {
+                        Some(sample) => *input_sample = sample?,
+                        None => break 'read,
+                    }
+                }
+
+                buffer.extend((0..channels).map(|channel| input_frame[channel % input_channels]));
+            }
+
+            if buffer.is_empty() {
+                break;
+            }
+
+            stream.process(&mut buffer, &mut on_message);
+
+            for sample in buffer.iter() {
+                writer.write_sample(*sample)?;
+            }
+        }
+
+        writer.finalize()?;
+
+        info!(
+            "Offline render complete ({} frames at {sample_rate}Hz)",
+            stream.position()
+        );
+
+        Ok(())
+    }
+
+    /// Returns a sender for messages that should be delivered before the next processed block
+    pub fn to_processor(&self) -> ToProcessorSender {
+        self.to_processor_sender.clone()
+    }
+
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// The number of frames that have been processed so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Schedules a message to be delivered to the processor at the given frame offset
+    ///
+    /// Messages scheduled for a frame that has already been processed will be delivered before
+    /// the next processed block.
+    pub fn schedule(&mut self, frame: usize, message: ToProcessor) {
+        // Messages scheduled for the same frame are delivered in the order they were scheduled
+        let index = self
+            .scheduled_messages
+            .partition_point(|scheduled| scheduled.frame <= frame);
+        self.scheduled_messages
+            .insert(index, ScheduledMessage { frame, message });
+    }
+
+    /// Processes an interleaved buffer with the stream's channel count in place
+    ///
+    /// Messages from the processor are passed to `on_message` after each processed block.
+    pub fn process(
+        &mut self,
+        buffer: &mut [f32],
+        mut on_message: impl FnMut(<M::Processor as AudioProcessor>::OutputMessage),
+    ) {
+        debug_assert_eq!(buffer.len() % self.channels, 0);
+
+        let mut remaining = buffer;
+
+        while !remaining.is_empty() {
+            // Deliver any messages that are due
+            while let Some(scheduled) = self.scheduled_messages.front() {
+                if scheduled.frame > self.position {
+                    break;
+                }
+
+                let scheduled = self.scheduled_messages.pop_front().unwrap();
+                if !self.to_processor_sender.push(scheduled.message) {
+                    warn!(
+                        "Failed to deliver scheduled message at frame {}",
+                        scheduled.frame
+                    );
+                }
+            }
+
+            // Process up to the next scheduled message
+            let mut block_frames = (remaining.len() / self.channels).min(self.frames_per_update);
+            if let Some(scheduled) = self.scheduled_messages.front() {
+                block_frames = block_frames.min(scheduled.frame - self.position);
+            }
+
+            let (block, rest) = remaining.split_at_mut(block_frames * self.channels);
+            self.processor.process_buffer(
+                block,
+                self.channels,
+                &self.to_processor_receiver,
+                &self.from_processor_sender,
+            );
+
+            // The messages are passed on before the channel to the processor fills up
+            while let Some(message) = self.from_processor_receiver.pop() {
+                on_message(message);
+            }
+
+            self.position += block_frames;
+            remaining = rest;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audio_module::{Parameter, Parameters};
+
+    /// Applies a gain that's set with parameter 0, and reports the size of each processed block
+    struct GainModule;
+
+    #[derive(Default)]
+    struct GainParameters;
+
+    impl Parameters for GainParameters {
+        fn parameters(&self) -> Vec<&dyn Parameter> {
+            Vec::new()
+        }
+
+        fn parameters_mut(&mut self) -> Vec<&mut dyn Parameter> {
+            Vec::new()
+        }
+    }
+
+    struct GainProcessor {
+        gain: f32,
+    }
+
+    impl AudioModule for GainModule {
+        const NAME: &'static str = "Gain";
+
+        type Parameters = GainParameters;
+        type Processor = GainProcessor;
+
+        fn create_processor(_sample_rate: usize) -> Self::Processor {
+            GainProcessor { gain: 1.0 }
+        }
+    }
+
+    impl AudioProcessor for GainProcessor {
+        type OutputMessage = usize;
+
+        fn process_buffer<To, From>(
+            &mut self,
+            buffer: &mut [f32],
+            channels: usize,
+            to_processor: &To,
+            from_processor: &From,
+        ) where
+            To: PopMessage<ToProcessor>,
+            From: PushMessage<Self::OutputMessage>,
+        {
+            while let Some(message) = to_processor.pop() {
+                if let ToProcessor::SetParameter(0, gain) = message {
+                    self.gain = gain;
+                }
+            }
+
+            for sample in buffer.iter_mut() {
+                *sample *= self.gain;
+            }
+
+            from_processor.push(buffer.len() / channels);
+        }
+    }
+
+    fn stream(frames_per_update: usize, channels: usize) -> OfflineStream<GainModule> {
+        OfflineStream::new(StreamConfig {
+            frames_per_update,
+            channels,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn scheduled_parameter_change_is_applied_at_its_frame() {
+        let mut stream = stream(64, 2);
+        stream.schedule(100, ToProcessor::SetParameter(0, 0.5));
+
+        let mut buffer = vec![1.0; 256 * 2];
+        let mut blocks = Vec::new();
+        stream.process(&mut buffer, |frames| blocks.push(frames));
+
+        let (before, after) = buffer.split_at(100 * 2);
+        assert!(before.iter().all(|sample| *sample == 1.0));
+        assert!(after.iter().all(|sample| *sample == 0.5));
+
+        // The block containing the change is split at the scheduled frame
+        assert_eq!(blocks, [64, 36, 64, 64, 28]);
+        assert_eq!(stream.position(), 256);
+    }
+
+    #[test]
+    fn all_processor_messages_are_delivered() {
+        // More blocks are processed than fit in the channel from the processor
+        let mut stream = stream(1, 1);
+        let mut buffer = vec![0.0; 3000];
+
+        let mut message_count = 0;
+        stream.process(&mut buffer, |_| message_count += 1);
+
+        assert_eq!(message_count, 3000);
+    }
+
+    #[test]
+    fn invalid_configs_are_rejected() {
+        for (frames_per_update, channels) in [(0, 2), (64, 0), (64, MAX_CHANNELS + 1)] {
+            let result = OfflineStream::<GainModule>::new(StreamConfig {
+                frames_per_update,
+                channels,
+                ..Default::default()
+            });
+            assert!(matches!(result, Err(Error::InvalidStreamConfig { .. })));
+        }
+    }
+
+    #[test]
+    fn render_maps_input_channels_to_the_configured_channels() {
+        let directory = std::env::temp_dir().join(format!("offline_render_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let input_path = directory.join("input.wav");
+        let output_path = directory.join("output.wav");
+
+        let mut writer = WavWriter::create(
+            &input_path,
+            WavSpec {
+                channels: 3,
+                sample_rate: 48000,
+                bits_per_sample: 32,
+                sample_format: SampleFormat::Float,
+            },
+        )
+        .unwrap();
+        for _ in 0..200 {
+            for sample in [0.1f32, 0.2, 0.3] {
+                writer.write_sample(sample).unwrap();
+            }
+        }
+        writer.finalize().unwrap();
+
+        let config = StreamConfig {
+            sample_rate: ProcessorSampleRate::FollowOutput,
+            frames_per_update: 32,
+            channels: 4,
+            ..Default::default()
+        };
+        let mut processed_frames = 0;
+        OfflineStream::<GainModule>::render(
+            &input_path,
+            &output_path,
+            config,
+            [ScheduledMessage::new(
+                150,
+                ToProcessor::SetParameter(0, 2.0),
+            )],
+            |frames| processed_frames += frames,
+        )
+        .unwrap();
+
+        let mut reader = WavReader::open(&output_path).unwrap();
+        assert_eq!(reader.spec().channels, 4);
+        assert_eq!(reader.spec().sample_rate, 48000);
+        let output: Vec<f32> = reader
+            .samples::<f32>()
+            .map(|sample| sample.unwrap())
+            .collect();
+        std::fs::remove_dir_all(&directory).ok();
+
+        assert_eq!(processed_frames, 200);
+        assert_eq!(output.len(), 200 * 4);
+        for (frame, samples) in output.chunks(4).enumerate() {
+            let gain: f32 = if frame < 150 { 1.0 } else { 2.0 };
+            assert_eq!(samples, [0.1 * gain, 0.2 * gain, 0.3 * gain, 0.1 * gain]);
+        }
+    }
+
+    #[test]
+    fn render_rejects_a_mismatched_sample_rate() {
+        let directory =
+            std::env::temp_dir().join(format!("offline_sample_rate_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let input_path = directory.join("input.wav");
+
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 48000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        WavWriter::create(&input_path, spec)
+            .unwrap()
+            .finalize()
+            .unwrap();
+
+        let config = StreamConfig {
+            sample_rate: ProcessorSampleRate::Fixed(44100),
+            ..Default::default()
+        };
+        let result = OfflineStream::<GainModule>::render(
+            &input_path,
+            directory.join("output.wav"),
+            config,
+            [],
+            |_| {},
+        );
+        std::fs::remove_dir_all(&directory).ok();
+
+        assert!(matches!(result, Err(Error::InvalidStreamConfig { .. })));
+    }
+}
@@ -0,0 +1,365 @@
+use super::{Error, Result, SharedStreamState};
+use crossbeam_channel::{Receiver, Sender, bounded};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use log::{error, info};
+use rtrb::{Consumer, Producer, RingBuffer};
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle, sleep},
+    time::{Duration, Instant},
+};
+
+/// The amount of audio that can be buffered between the processor and the writer thread
+const RING_BUFFER_DURATION: Duration = Duration::from_secs(1);
+
+/// How often the writer thread checks for recorded audio
+const WRITE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long the writer thread waits for the processor to stop writing when a recording is stopped
+///
+/// The processor might not be running, in which case the recording is finalized after the timeout.
+const STOP_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The number of commands that can be queued for the processor
+const COMMAND_QUEUE_SIZE: usize = 8;
+
+/// Commands that are sent from the [Recorder] to the processor's [RecordingTap]
+pub(crate) enum RecorderCommand {
+    Start {
+        producer: Producer<f32>,
+        include_input: bool,
+    },
+    Stop,
+}
+
+/// Controls recordings of the processor's output, see `AudioStream::start_recording`
+///
+/// When the processor is finished with a recording's ring buffer producer it returns it via the
+/// `retired` channel, so that the ring buffer is never freed on the audio thread.
+pub(crate) struct Recorder {
+    commands: Sender<RecorderCommand>,
+    shared: Arc<SharedStreamState>,
+    retired: Receiver<Producer<f32>>,
+    sample_rate: usize,
+    channels: usize,
+    output_only: bool,
+    recording: Option<Recording>,
+}
+
+struct Recording {
+    path: PathBuf,
+    stop_flag: Arc<AtomicBool>,
+    writer_thread: JoinHandle<Result<()>>,
+}
+
+impl Recorder {
+    /// Creates a recorder along with the tap that should be owned by the processor
+    pub fn new(
+        sample_rate: usize,
+        frames_per_update: usize,
+        channels: usize,
+        output_only: bool,
+        shared: Arc<SharedStreamState>,
+    ) -> (Self, RecordingTap) {
+        // Commands are rare, so a small queue is enough
+        let (command_sender, command_receiver) = bounded(COMMAND_QUEUE_SIZE);
+        // Each producer is retired once, either by a stop command or by being replaced
+        let (retired_sender, retired_receiver) = bounded(COMMAND_QUEUE_SIZE + 1);
+
+        let recorder = Self {
+            commands: command_sender,
+            shared,
+            retired: retired_receiver,
+            sample_rate,
+            channels,
+            output_only,
+            recording: None,
+        };
+        let tap = RecordingTap::new(
+            command_receiver,
+            retired_sender,
+            frames_per_update,
+            channels,
+        );
+
+        (recorder, tap)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Starts a new recording
+    ///
+    /// `Error::StreamNotRunning` is returned without creating a file if the stream's devices
+    /// aren't running, and an error is also returned if the processor's command queue is full.
+    pub fn start(&mut self, path: &Path, include_input: bool) -> Result<()> {
+        if self.recording.is_some() {
+            return Err(Error::RecordingInProgress);
+        }
+        if !self.shared.is_running() {
+            return Err(Error::StreamNotRunning);
+        }
+
+        // Free producers from earlier recordings that the processor has finished with
+        self.retired.try_iter().for_each(drop);
+
+        // Output-only streams don't have any input to record
+        let include_input = include_input && !self.output_only;
+        let recorded_channels = if include_input {
+            self.channels * 2
+        } else {
+            self.channels
+        };
+
+        let writer = WavWriter::create(
+            path,
+            WavSpec {
+                channels: recorded_channels as u16,
+                sample_rate: self.sample_rate as u32,
+                bits_per_sample: 32,
+                sample_format: SampleFormat::Float,
+            },
+        )?;
+
+        let capacity = (RING_BUFFER_DURATION.as_secs_f64() * self.sample_rate as f64) as usize
+            * recorded_channels;
+        let (producer, consumer) = RingBuffer::new(capacity);
+
+        if self
+            .commands
+            .try_send(RecorderCommand::Start {
+                producer,
+                include_input,
+            })
+            .is_err()
+        {
+            // Nothing has been written yet, so the empty file is removed
+            drop(writer);
+            fs::remove_file(path).ok();
+            return Err(Error::RecorderCommandSendFailed);
+        }
+
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let writer_thread = {
+            thread::Builder::new()
+                .name("audio_recorder".to_string())
+                .spawn({
+                    let stop_flag = stop_flag.clone();
+                    let retired = self.retired.clone();
+                    move || writer_thread(consumer, writer, retired, stop_flag)
+                })?
+        };
+
+        info!("Recording to {}", path.display());
+
+        self.recording = Some(Recording {
+            path: path.to_path_buf(),
+            stop_flag,
+            writer_thread,
+        });
+
+        Ok(())
+    }
+
+    /// Stops the current recording, returning the recorded file's path
+    ///
+    /// The writer thread waits for the processor to stop writing (see [STOP_TIMEOUT]), and then
+    /// any audio that's waiting to be written is flushed before the file is finalized.
+    pub fn stop(&mut self) -> Result<Option<PathBuf>> {
+        let Some(recording) = self.recording.take() else {
+            return Ok(None);
+        };
+
+        // If the command can't be sent then the processor isn't running, and the writer thread
+        // will stop after the timeout.
+        self.commands.try_send(RecorderCommand::Stop).ok();
+        recording.stop_flag.store(true, Ordering::Relaxed);
+
+        match recording.writer_thread.join() {
+            Ok(result) => result?,
+            Err(_) => error!("The recorder's writer thread panicked"),
+        }
+
+        info!("Finished recording to {}", recording.path.display());
+
+        Ok(Some(recording.path))
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(error) = self.stop() {
+            error!("Failed to stop recording: {error}");
+        }
+    }
+}
+
+/// Taps the processor's audio for the [Recorder]
+///
+/// The tap is owned by the processor, audio is passed to the writer thread via a lock-free ring
+/// buffer so that it can be used in realtime contexts.
+pub(crate) struct RecordingTap {
+    commands: Receiver<RecorderCommand>,
+    retired: Sender<Producer<f32>>,
+    producer: Option<Producer<f32>>,
+    include_input: bool,
+    channels: usize,
+    // A copy of the processor's dry input
+    input: Vec<f32>,
+}
+
+impl RecordingTap {
+    fn new(
+        commands: Receiver<RecorderCommand>,
+        retired: Sender<Producer<f32>>,
+        frames_per_update: usize,
+        channels: usize,
+    ) -> Self {
+        Self {
+            commands,
+            retired,
+            producer: None,
+            include_input: false,
+            channels,
+            input: vec![0.0; frames_per_update * channels],
+        }
+    }
+
+    /// Returns a new tap that receives commands from the same [Recorder]
+    ///
+    /// This is used when a tap has been lost along with its processor, any recording that was in
+    /// progress is ended.
+    pub fn detached(&self) -> Self {
+        Self::new(
+            self.commands.clone(),
+            self.retired.clone(),
+            self.input.len() / self.channels,
+            self.channels,
+        )
+    }
+
+    /// Handles incoming commands and captures the processor's input
+    ///
+    /// This should be called before the processor is given `buffer`.
+    pub fn capture_input(&mut self, buffer: &[f32]) {
+        while let Ok(command) = self.commands.try_recv() {
+            match command {
+                RecorderCommand::Start {
+                    producer,
+                    include_input,
+                } => {
+                    self.retire_producer();
+                    self.producer = Some(producer);
+                    self.include_input = include_input;
+                }
+                RecorderCommand::Stop => self.retire_producer(),
+            }
+        }
+
+        if self.producer.is_some() && self.include_input {
+            self.input[..buffer.len()].copy_from_slice(buffer);
+        }
+    }
+
+    /// Returns the current producer to the [Recorder] so that it isn't dropped on the audio thread
+    fn retire_producer(&mut self) {
+        if let Some(producer) = self.producer.take() {
+            // There's always space in the channel, see `Recorder::new`
+            self.retired.try_send(producer).ok();
+        }
+    }
+
+    /// Records the processor's output, along with the captured input if it's being recorded
+    ///
+    /// If the writer thread isn't keeping up then the block is dropped, and the number of dropped
+    /// frames is returned.
+    pub fn record_output(&mut self, buffer: &[f32]) -> Option<usize> {
+        let producer = self.producer.as_mut()?;
+
+        let frames = buffer.len() / self.channels;
+        let recorded_channels = if self.include_input {
+            self.channels * 2
+        } else {
+            self.channels
+        };
+
+        let Ok(chunk) = producer.write_chunk_uninit(frames * recorded_channels) else {
+            return Some(frames);
+        };
+
+        // Output channels are followed by the input channels in each recorded frame
+        let input = &self.input[..buffer.len()];
+        let include_input = self.include_input;
+        chunk.fill_from_iter(
+            buffer
+                .chunks_exact(self.channels)
+                .zip(input.chunks_exact(self.channels))
+                .flat_map(|(output_frame, input_frame)| {
+                    let input_frame = if include_input { input_frame } else { &[] };
+                    output_frame.iter().chain(input_frame).copied()
+                }),
+        );
+
+        None
+    }
+}
+
+fn writer_thread(
+    mut consumer: Consumer<f32>,
+    mut writer: WavWriter<BufWriter<File>>,
+    retired: Receiver<Producer<f32>>,
+    stop_flag: Arc<AtomicBool>,
+) -> Result<()> {
+    while !stop_flag.load(Ordering::Relaxed) && !consumer.is_abandoned() {
+        if write_available(&mut consumer, &mut writer)? == 0 {
+            sleep(WRITE_INTERVAL);
+        }
+    }
+
+    // Wait for the processor to return the producer, after which nothing more will be written to
+    // the ring buffer. Producers from earlier recordings have lost their consumers, so they can
+    // be dropped while waiting.
+    let stop_deadline = Instant::now() + STOP_TIMEOUT;
+    let mut producer = None;
+    while producer.is_none() && !consumer.is_abandoned() {
+        match retired.recv_deadline(stop_deadline) {
+            Ok(retired) if !retired.is_abandoned() => producer = Some(retired),
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+
+    // Write the audio that remains in the ring buffer before finalizing the file
+    write_available(&mut consumer, &mut writer)?;
+    writer.finalize()?;
+
+    Ok(())
+}
+
+/// Writes the audio that's available in the ring buffer, returning the number of written samples
+fn write_available(
+    consumer: &mut Consumer<f32>,
+    writer: &mut WavWriter<BufWriter<File>>,
+) -> Result<usize> {
+    let available = consumer.slots();
+    if available == 0 {
+        return Ok(0);
+    }
+
+    if let Ok(chunk) = consumer.read_chunk(available) {
+        let (first, second) = chunk.as_slices();
+        for sample in first.iter().chain(second) {
+            writer.write_sample(*sample)?;
+        }
+        chunk.commit_all();
+    }
+
+    Ok(available)
+}
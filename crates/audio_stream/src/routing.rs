@@ -0,0 +1,388 @@
+use super::{Error, Result};
+use crate::ChannelRouting;
+
+/// Maps an input device's channels to the processor's channels, see [ChannelRouting::input]
+pub(crate) struct InputMap {
+    // The device channel for each processor channel
+    sources: Vec<Option<usize>>,
+    device_channels: usize,
+}
+
+impl InputMap {
+    pub fn new(
+        routing: &ChannelRouting,
+        device_channels: usize,
+        processor_channels: usize,
+    ) -> Result<Self> {
+        let sources = if routing.input.is_empty() {
+            (0..processor_channels)
+                .map(|channel| match device_channels {
+                    1 => Some(0),
+                    _ => (channel < device_channels).then_some(channel),
+                })
+                .collect()
+        } else {
+            routing.input.clone()
+        };
+
+        check_channels(&sources, device_channels, "input")?;
+
+        Ok(Self {
+            sources,
+            device_channels,
+        })
+    }
+
+    pub fn processor_channels(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// True if the device's frames can be passed to the processor unchanged
+    pub fn is_identity(&self) -> bool {
+        self.sources.len() == self.device_channels
+            && self
+                .sources
+                .iter()
+                .enumerate()
+                .all(|(channel, source)| *source == Some(channel))
+    }
+
+    /// Fills the processor's interleaved `buffer` from the device's interleaved `data`
+    ///
+    /// Returns the number of frames that were written to the buffer.
+    pub fn apply(&self, data: &[f32], buffer: &mut [f32]) -> usize {
+        let processor_channels = self.sources.len();
+        let mut frames = 0;
+
+        for (input_frame, buffer_frame) in data
+            .chunks_exact(self.device_channels)
+            .zip(buffer.chunks_exact_mut(processor_channels))
+        {
+            for (buffer_sample, source) in buffer_frame.iter_mut().zip(self.sources.iter()) {
+                *buffer_sample = source.map_or(0.0, |source| input_frame[source]);
+            }
+            frames += 1;
+        }
+
+        frames
+    }
+
+    /// Maps all of the device's interleaved `data` to the processor's channels, a `buffer` at a
+    /// time
+    ///
+    /// `send` is called with each filled part of the buffer, so device callbacks with more frames
+    /// than the buffer are passed on in full.
+    pub fn apply_in_chunks(&self, data: &[f32], buffer: &mut [f32], mut send: impl FnMut(&[f32])) {
+        let processor_channels = self.sources.len();
+        let buffer_frames = buffer.len() / processor_channels;
+        debug_assert!(buffer_frames > 0);
+
+        for chunk in data.chunks(buffer_frames * self.device_channels) {
+            let frames = self.apply(chunk, buffer);
+            send(&buffer[..frames * processor_channels]);
+        }
+    }
+}
+
+/// Maps the processor's channels to an output device's channels, see [ChannelRouting::output]
+pub(crate) struct OutputMap {
+    // The device channel for each processor channel
+    targets: Vec<Option<usize>>,
+    // The downmix gain for each processor channel
+    gains: Vec<f32>,
+    device_channels: usize,
+}
+
+impl OutputMap {
+    pub fn new(
+        routing: &ChannelRouting,
+        device_channels: usize,
+        processor_channels: usize,
+    ) -> Result<Self> {
+        let targets: Vec<_> = if routing.output.is_empty() {
+            (0..processor_channels)
+                .map(|channel| match device_channels {
+                    1 => Some(0),
+                    _ => (channel < device_channels).then_some(channel),
+                })
+                .collect()
+        } else {
+            routing.output.clone()
+        };
+
+        check_channels(&targets, device_channels, "output")?;
+
+        // Processor channels that share an output channel are scaled by the downmix law
+        let gains = targets
+            .iter()
+            .map(|target| {
+                let shared = targets.iter().filter(|other| *other == target).count();
+                routing.downmix.gain(shared)
+            })
+            .collect();
+
+        Ok(Self {
+            targets,
+            gains,
+            device_channels,
+        })
+    }
+
+    pub fn processor_channels(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// True if the processor's frames can be passed to the device unchanged
+    pub fn is_identity(&self) -> bool {
+        self.targets.len() == self.device_channels
+            && self
+                .targets
+                .iter()
+                .enumerate()
+                .all(|(channel, target)| *target == Some(channel))
+    }
+
+    /// Fills the device's interleaved `data` from the processor's interleaved `buffer`
+    pub fn apply(&self, buffer: &[f32], data: &mut [f32]) {
+        let processor_channels = self.targets.len();
+
+        for (output_frame, processed_frame) in data
+            .chunks_exact_mut(self.device_channels)
+            .zip(buffer.chunks_exact(processor_channels))
+        {
+            // Any output channels without a processor channel are silent
+            output_frame.fill(0.0);
+
+            for ((processed_sample, target), gain) in processed_frame
+                .iter()
+                .zip(self.targets.iter())
+                .zip(self.gains.iter())
+            {
+                if let Some(target) = target {
+                    output_frame[*target] += processed_sample * gain;
+                }
+            }
+        }
+    }
+
+    /// Fills all of the device's interleaved `data`, with `read` filling `buffer` with the
+    /// processor's output for each part of the data
+    ///
+    /// Device callbacks with more frames than the buffer are filled in full.
+    pub fn apply_in_chunks(
+        &self,
+        buffer: &mut [f32],
+        data: &mut [f32],
+        mut read: impl FnMut(&mut [f32]),
+    ) {
+        let processor_channels = self.targets.len();
+        let buffer_frames = buffer.len() / processor_channels;
+        debug_assert!(buffer_frames > 0);
+
+        for chunk in data.chunks_mut(buffer_frames * self.device_channels) {
+            let frames = chunk.len() / self.device_channels;
+            let buffer = &mut buffer[..frames * processor_channels];
+            read(buffer);
+            self.apply(buffer, chunk);
+        }
+    }
+}
+
+fn check_channels(
+    routes: &[Option<usize>],
+    device_channels: usize,
+    stream: &'static str,
+) -> Result<()> {
+    match routes
+        .iter()
+        .flatten()
+        .find(|channel| **channel >= device_channels)
+    {
+        Some(channel) => Err(Error::InvalidChannelRoute {
+            stream,
+            channel: *channel,
+            device_channels,
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DownmixLaw;
+
+    fn routing(input: &[Option<usize>], output: &[Option<usize>]) -> ChannelRouting {
+        ChannelRouting {
+            input: input.to_vec(),
+            output: output.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 1.0e-6,
+                "{actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn mono_input_feeds_every_processor_channel() {
+        let map = InputMap::new(&ChannelRouting::default(), 1, 2).unwrap();
+        assert!(!map.is_identity());
+        assert_eq!(map.processor_channels(), 2);
+
+        let mut buffer = [0.0; 6];
+        assert_eq!(map.apply(&[0.1, 0.2, 0.3], &mut buffer), 3);
+        assert_eq!(buffer, [0.1, 0.1, 0.2, 0.2, 0.3, 0.3]);
+    }
+
+    #[test]
+    fn matching_input_channels_are_passed_through() {
+        let map = InputMap::new(&ChannelRouting::default(), 2, 2).unwrap();
+        assert!(map.is_identity());
+
+        // Processor channels without a matching device channel are silent
+        let map = InputMap::new(&ChannelRouting::default(), 2, 3).unwrap();
+        assert!(!map.is_identity());
+        let mut buffer = [1.0; 3];
+        assert_eq!(map.apply(&[0.1, 0.2], &mut buffer), 1);
+        assert_eq!(buffer, [0.1, 0.2, 0.0]);
+    }
+
+    #[test]
+    fn chosen_input_channels_are_used_from_a_multichannel_device() {
+        let routing = routing(&[Some(3), None, Some(1)], &[]);
+        let map = InputMap::new(&routing, 4, 3).unwrap();
+        assert_eq!(map.processor_channels(), 3);
+
+        let data = [0.0, 0.1, 0.2, 0.3, 1.0, 1.1, 1.2, 1.3];
+        let mut buffer = [1.0; 6];
+        assert_eq!(map.apply(&data, &mut buffer), 2);
+        assert_eq!(buffer, [0.3, 0.0, 0.1, 1.3, 0.0, 1.1]);
+    }
+
+    #[test]
+    fn device_input_larger_than_the_buffer_is_passed_on_in_chunks() {
+        let map = InputMap::new(&ChannelRouting::default(), 1, 2).unwrap();
+
+        // 5 mono frames from the device, with room for 2 stereo frames in the buffer
+        let mut buffer = [0.0; 4];
+        let mut chunks = Vec::new();
+        map.apply_in_chunks(&[0.1, 0.2, 0.3, 0.4, 0.5], &mut buffer, |chunk| {
+            chunks.push(chunk.to_vec())
+        });
+
+        assert_eq!(
+            chunks,
+            [
+                vec![0.1, 0.1, 0.2, 0.2],
+                vec![0.3, 0.3, 0.4, 0.4],
+                vec![0.5, 0.5],
+            ]
+        );
+    }
+
+    #[test]
+    fn device_output_larger_than_the_buffer_is_filled_in_chunks() {
+        let map = OutputMap::new(&routing(&[], &[Some(1), Some(0)]), 2, 2).unwrap();
+
+        // 5 stereo frames for the device, with room for 2 frames in the buffer
+        let mut buffer = [0.0; 4];
+        let mut data = [f32::NAN; 10];
+        let mut next_frame = 0;
+        let mut read_frames = Vec::new();
+        map.apply_in_chunks(&mut buffer, &mut data, |buffer| {
+            read_frames.push(buffer.len() / 2);
+            for frame in buffer.chunks_mut(2) {
+                next_frame += 1;
+                frame.copy_from_slice(&[next_frame as f32, -next_frame as f32]);
+            }
+        });
+
+        assert_eq!(read_frames, [2, 2, 1]);
+        assert_eq!(
+            data,
+            [-1.0, 1.0, -2.0, 2.0, -3.0, 3.0, -4.0, 4.0, -5.0, 5.0]
+        );
+    }
+
+    #[test]
+    fn routes_to_missing_device_channels_are_rejected() {
+        let routing = routing(&[Some(0), Some(2)], &[Some(0), Some(4)]);
+        assert!(matches!(
+            InputMap::new(&routing, 2, 2),
+            Err(Error::InvalidChannelRoute {
+                stream: "input",
+                channel: 2,
+                device_channels: 2,
+            })
+        ));
+        assert!(matches!(
+            OutputMap::new(&routing, 4, 2),
+            Err(Error::InvalidChannelRoute {
+                stream: "output",
+                channel: 4,
+                device_channels: 4,
+            })
+        ));
+    }
+
+    #[test]
+    fn stereo_output_is_spread_over_a_multichannel_device() {
+        let map = OutputMap::new(&routing(&[], &[Some(3), Some(1)]), 4, 2).unwrap();
+        assert!(!map.is_identity());
+        assert_eq!(map.processor_channels(), 2);
+
+        let mut data = [1.0; 8];
+        map.apply(&[0.1, 0.2, 0.3, 0.4], &mut data);
+        assert_eq!(data, [0.0, 0.2, 0.0, 0.1, 0.0, 0.4, 0.0, 0.3]);
+    }
+
+    #[test]
+    fn discarded_processor_channels_are_not_output() {
+        let map = OutputMap::new(&routing(&[], &[None, Some(0)]), 2, 2).unwrap();
+
+        let mut data = [1.0; 2];
+        map.apply(&[0.1, 0.2], &mut data);
+        assert_eq!(data, [0.2, 0.0]);
+    }
+
+    #[test]
+    fn downmixed_channels_are_scaled_by_the_downmix_law() {
+        let buffer = [0.5, 0.25];
+        for (downmix, gain) in [
+            (DownmixLaw::Sum, 1.0),
+            (DownmixLaw::Average, 0.5),
+            (DownmixLaw::EqualPower, 0.5f32.sqrt()),
+        ] {
+            // Stereo is mixed to a mono device by default
+            let routing = ChannelRouting {
+                downmix,
+                ..Default::default()
+            };
+            let map = OutputMap::new(&routing, 1, 2).unwrap();
+            let mut data = [0.0];
+            map.apply(&buffer, &mut data);
+            assert_close(&data, &[0.75 * gain]);
+        }
+    }
+
+    #[test]
+    fn only_shared_output_channels_are_downmixed() {
+        let routing = ChannelRouting {
+            output: vec![Some(0), Some(0), Some(0), Some(1)],
+            downmix: DownmixLaw::Average,
+            ..Default::default()
+        };
+        let map = OutputMap::new(&routing, 2, 4).unwrap();
+
+        let mut data = [0.0; 2];
+        map.apply(&[0.3, 0.6, 0.9, 0.5], &mut data);
+        assert_close(&data, &[0.6, 0.5]);
+    }
+}
@@ -0,0 +1,83 @@
+use super::{Error, FromProcessorSender, ProcessorTaps, SharedStreamState, ToProcessorReceiver};
+use audio_module::AudioProcessor;
+use crossbeam_channel::Sender;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Runs a processor over blocks of interleaved audio, along with its taps
+///
+/// The same runner is used wherever the processor runs, whether that's the processor thread, an
+/// output stream's callback, a JACK client, or a `TestBackend`.
+pub(crate) struct ProcessorRunner<P: AudioProcessor> {
+    processor: P,
+    taps: ProcessorTaps,
+    channels: usize,
+    sample_rate: usize,
+    to_processor: ToProcessorReceiver,
+    from_processor: FromProcessorSender<P>,
+    error_sender: Sender<Error>,
+    shared: Arc<SharedStreamState>,
+}
+
+impl<P: AudioProcessor> ProcessorRunner<P> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        processor: P,
+        taps: ProcessorTaps,
+        channels: usize,
+        sample_rate: usize,
+        to_processor: ToProcessorReceiver,
+        from_processor: FromProcessorSender<P>,
+        error_sender: Sender<Error>,
+        shared: Arc<SharedStreamState>,
+    ) -> Self {
+        Self {
+            processor,
+            taps,
+            channels,
+            sample_rate,
+            to_processor,
+            from_processor,
+            error_sender,
+            shared,
+        }
+    }
+
+    /// Processes a block of interleaved audio in place
+    ///
+    /// `input_received` should be false when no input was available for the block, in which case
+    /// the block is only processed if a file or test signal is replacing the device input.
+    /// Returns true if the block was processed.
+    pub fn process(&mut self, buffer: &mut [f32], input_received: bool) -> bool {
+        // Files and test signals replace the device input
+        let input_replaced = self.taps.input_source.fill(buffer);
+        if !(input_received || input_replaced) {
+            return false;
+        }
+
+        self.taps.recording.capture_input(buffer);
+
+        let frames = buffer.len() / self.channels;
+        let processing_start = Instant::now();
+        self.processor.process_buffer(
+            buffer,
+            self.channels,
+            &self.to_processor,
+            &self.from_processor,
+        );
+        self.shared.load_meter().record(
+            processing_start.elapsed(),
+            Duration::from_secs_f64(frames as f64 / self.sample_rate as f64),
+        );
+
+        if let Some(frames) = self.taps.recording.record_output(buffer) {
+            self.error_sender
+                .try_send(Error::RecordingOverflow { frames })
+                .ok();
+        }
+
+        true
+    }
+}
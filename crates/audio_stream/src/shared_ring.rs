@@ -0,0 +1,135 @@
+use js_sys::{Atomics, Int32Array, Reflect, SharedArrayBuffer, Uint8Array};
+
+/// The size of the ring's header, which contains the read and write positions
+const HEADER_BYTES: u32 = 8;
+const READ_INDEX: u32 = 0;
+const WRITE_INDEX: u32 = 1;
+/// Each message is prefixed with its length
+const LENGTH_BYTES: u32 = 4;
+
+/// Returns true if `SharedArrayBuffer`s can be shared with the audio worklet
+///
+/// Sharing memory requires the page to be cross-origin isolated, i.e. served with the
+/// `Cross-Origin-Opener-Policy: same-origin` and `Cross-Origin-Embedder-Policy: require-corp`
+/// headers.
+pub(crate) fn shared_memory_available() -> bool {
+    Reflect::get(&js_sys::global(), &"crossOriginIsolated".into())
+        .ok()
+        .and_then(|isolated| isolated.as_bool())
+        .unwrap_or(false)
+}
+
+/// A lock-free single-producer single-consumer ring of byte messages in a `SharedArrayBuffer`
+///
+/// The ring is used to pass messages between the main thread and the audio worklet, which run in
+/// separate wasm instances, so the ring's state lives entirely in the shared buffer. The read and
+/// write positions increase continuously and wrap at `u32::MAX`, which is why the capacity needs
+/// to be a power of two.
+#[derive(Clone, PartialEq)]
+pub(crate) struct SharedRing {
+    buffer: SharedArrayBuffer,
+    header: Int32Array,
+    data: Uint8Array,
+    capacity: u32,
+}
+
+impl SharedRing {
+    /// Creates a ring that can hold at least `capacity` bytes of messages
+    pub fn new(capacity: u32) -> Self {
+        let capacity = capacity.next_power_of_two();
+        Self::from_buffer(SharedArrayBuffer::new(HEADER_BYTES + capacity))
+    }
+
+    /// Accesses a ring that was created with [SharedRing::new], e.g. after it's been passed to the
+    /// audio worklet
+    pub fn from_buffer(buffer: SharedArrayBuffer) -> Self {
+        let capacity = buffer.byte_length() - HEADER_BYTES;
+        debug_assert!(capacity.is_power_of_two());
+
+        Self {
+            header: Int32Array::new_with_byte_offset_and_length(&buffer, 0, 2),
+            data: Uint8Array::new_with_byte_offset_and_length(&buffer, HEADER_BYTES, capacity),
+            buffer,
+            capacity,
+        }
+    }
+
+    /// The ring's shared buffer, for passing to the other side of the ring
+    pub fn buffer(&self) -> &SharedArrayBuffer {
+        &self.buffer
+    }
+
+    /// Pushes a message, returning false if there isn't enough space for it in the ring
+    ///
+    /// This should only be called by the ring's producer.
+    pub fn push(&self, message: &[u8]) -> bool {
+        let read = self.load(READ_INDEX);
+        let write = self.load(WRITE_INDEX);
+        let available = self.capacity - write.wrapping_sub(read);
+        let size = LENGTH_BYTES + message.len() as u32;
+        if size > available {
+            return false;
+        }
+
+        self.write_bytes(write, &(message.len() as u32).to_le_bytes());
+        self.write_bytes(write.wrapping_add(LENGTH_BYTES), message);
+        self.store(WRITE_INDEX, write.wrapping_add(size));
+
+        true
+    }
+
+    /// Pops the next message into `message`, returning false if the ring is empty
+    ///
+    /// This should only be called by the ring's consumer.
+    pub fn pop(&self, message: &mut Vec<u8>) -> bool {
+        let read = self.load(READ_INDEX);
+        let write = self.load(WRITE_INDEX);
+        if read == write {
+            return false;
+        }
+
+        let mut length = [0; LENGTH_BYTES as usize];
+        self.read_bytes(read, &mut length);
+        let length = u32::from_le_bytes(length);
+
+        message.resize(length as usize, 0);
+        self.read_bytes(read.wrapping_add(LENGTH_BYTES), message);
+        self.store(READ_INDEX, read.wrapping_add(LENGTH_BYTES + length));
+
+        true
+    }
+
+    fn load(&self, index: u32) -> u32 {
+        Atomics::load(&self.header, index).unwrap_or(0) as u32
+    }
+
+    fn store(&self, index: u32, value: u32) {
+        Atomics::store(&self.header, index, value as i32).ok();
+    }
+
+    /// Copies bytes into the ring starting at `position`, wrapping around the end of the ring
+    fn write_bytes(&self, position: u32, bytes: &[u8]) {
+        let start = position % self.capacity;
+        let first_len = (bytes.len() as u32).min(self.capacity - start);
+        let (first, second) = bytes.split_at(first_len as usize);
+
+        self.data
+            .subarray(start, start + first_len)
+            .copy_from(first);
+        if !second.is_empty() {
+            self.data.subarray(0, second.len() as u32).copy_from(second);
+        }
+    }
+
+    /// Copies bytes out of the ring starting at `position`, wrapping around the end of the ring
+    fn read_bytes(&self, position: u32, bytes: &mut [u8]) {
+        let start = position % self.capacity;
+        let first_len = (bytes.len() as u32).min(self.capacity - start);
+        let (first, second) = bytes.split_at_mut(first_len as usize);
+
+        self.data.subarray(start, start + first_len).copy_to(first);
+        if !second.is_empty() {
+            self.data.subarray(0, second.len() as u32).copy_to(second);
+        }
+    }
+}
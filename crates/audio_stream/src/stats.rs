@@ -0,0 +1,324 @@
+use super::{DeviceDirection, Error};
+use crate::{LoadMeter, ProcessorLoad, ResamplerQuality};
+use crossbeam_channel::Sender;
+use std::{
+    fmt,
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+/// The channels that carry audio between the devices and the processor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamChannel {
+    InputToProcessor,
+    ProcessorToOutput,
+}
+
+impl fmt::Display for StreamChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InputToProcessor => f.write_str("input -> processor"),
+            Self::ProcessorToOutput => f.write_str("processor -> output"),
+        }
+    }
+}
+
+/// The kinds of xruns that can occur in a [StreamChannel]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XrunKind {
+    Underflow,
+    Overflow,
+}
+
+/// Xrun counts for one of the channels between the devices and the processor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelStats {
+    pub underflows: u64,
+    pub overflows: u64,
+}
+
+impl ChannelStats {
+    /// The total number of underflows and overflows
+    pub fn xruns(&self) -> u64 {
+        self.underflows + self.overflows
+    }
+}
+
+/// The config that's being used by a device stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceConfig {
+    pub device_name: String,
+    pub channels: u16,
+    pub sample_rate: u32,
+    /// The requested buffer size in frames
+    pub buffer_size: u32,
+}
+
+/// The settings and measured latency of one of the stream's resampling channels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResamplerStats {
+    pub quality: ResamplerQuality,
+    /// The channel's target latency
+    pub target_latency: Duration,
+    /// The channel's capacity
+    pub capacity: Duration,
+    /// The amount of audio that was buffered in the channel at the processor's last update
+    pub measured_latency: Duration,
+}
+
+/// A snapshot of a stream's health, see `AudioStream::stats`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamStats {
+    pub input_to_processor: ChannelStats,
+    pub processor_to_output: ChannelStats,
+    /// The number of xruns that were reported by the audio server, e.g. by JACK
+    pub server_xruns: u64,
+    /// The most recent error that was reported by the stream
+    pub last_error: Option<String>,
+    /// The input device config, `None` for output-only streams or when the streams aren't running
+    pub input_config: Option<DeviceConfig>,
+    /// The output device config, `None` when the streams aren't running
+    pub output_config: Option<DeviceConfig>,
+    /// The processor's load since the stream was created
+    pub processor_load: ProcessorLoad,
+    /// The input resampling channel, `None` for output-only streams
+    pub input_resampler: Option<ResamplerStats>,
+    /// The output resampling channel
+    ///
+    /// This is `None` when the processor runs in the output callback.
+    pub output_resampler: Option<ResamplerStats>,
+    /// The estimated round-trip latency, see `AudioStream::latency`
+    pub latency: Option<Duration>,
+}
+
+/// Events that are reported while a stream is running, see `AudioStream::events`
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    /// The device streams have been started, either initially or after a config change
+    Started {
+        input: Option<DeviceConfig>,
+        output: DeviceConfig,
+        latency: Duration,
+    },
+    /// The device streams have been stopped following a change to the devices
+    Stopped,
+    /// The device streams have been restarted following a change to the devices
+    ///
+    /// This follows the [StreamEvent::Started] event for the new streams.
+    StreamRestarted,
+    /// The device streams failed to start
+    ///
+    /// Depending on the stream's `DevicePolicy`, starting the streams will be retried when the
+    /// devices change.
+    StreamFailed(String),
+    /// A device has been connected to the host
+    DeviceAdded(String),
+    /// A device has been disconnected from the host
+    DeviceRemoved(String),
+    /// The host's default device has changed, `None` when there's no default device
+    DefaultDeviceChanged {
+        direction: DeviceDirection,
+        name: Option<String>,
+    },
+    /// An underflow or overflow occurred in one of the stream's channels
+    Xrun {
+        channel: StreamChannel,
+        kind: XrunKind,
+        frames: usize,
+    },
+    /// The audio server reported an xrun, e.g. JACK's process callback missed its deadline
+    ServerXrun,
+    /// An error was reported by the stream
+    Error(String),
+}
+
+/// The details of a started set of device streams
+pub(crate) struct StartedStreams {
+    pub input: Option<DeviceConfig>,
+    pub output: DeviceConfig,
+    pub input_resampler: Option<ResamplerStats>,
+    pub output_resampler: Option<ResamplerStats>,
+    pub latency: Duration,
+}
+
+/// State that's shared between a stream's threads and the `AudioStream`
+pub(crate) struct SharedStreamState {
+    stats: Mutex<StreamStats>,
+    load_meter: LoadMeter,
+    // The measured resampler latencies in seconds, stored as `f64` bits
+    input_resampler_latency: AtomicU64,
+    output_resampler_latency: AtomicU64,
+    // Set once the streams have been started, after which starts are reported as restarts
+    started_once: AtomicBool,
+    // Set while the streams are running and the processor is picking up commands
+    running: AtomicBool,
+    events: Sender<StreamEvent>,
+}
+
+impl SharedStreamState {
+    pub fn new(events: Sender<StreamEvent>) -> Self {
+        Self {
+            stats: Mutex::default(),
+            load_meter: LoadMeter::new(),
+            input_resampler_latency: AtomicU64::new(0),
+            output_resampler_latency: AtomicU64::new(0),
+            started_once: AtomicBool::new(false),
+            running: AtomicBool::new(false),
+            events,
+        }
+    }
+
+    pub fn stats(&self) -> StreamStats {
+        let mut stats = self
+            .stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        stats.processor_load = self.load_meter.snapshot();
+        if let Some(resampler) = &mut stats.input_resampler {
+            resampler.measured_latency = load_seconds(&self.input_resampler_latency);
+        }
+        if let Some(resampler) = &mut stats.output_resampler {
+            resampler.measured_latency = load_seconds(&self.output_resampler_latency);
+        }
+        stats
+    }
+
+    pub fn latency(&self) -> Option<Duration> {
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .latency
+    }
+
+    pub fn load_meter(&self) -> &LoadMeter {
+        &self.load_meter
+    }
+
+    /// Records the amount of audio that's buffered in one of the resampling channels
+    pub fn record_resampler_latency(&self, channel: StreamChannel, seconds: f64) {
+        let latency = match channel {
+            StreamChannel::InputToProcessor => &self.input_resampler_latency,
+            StreamChannel::ProcessorToOutput => &self.output_resampler_latency,
+        };
+        latency.store(seconds.to_bits(), Ordering::Relaxed);
+    }
+
+    /// True while the streams are running, i.e. between `streams_started` and `streams_stopped`
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// Updates the stats with a started set of streams
+    ///
+    /// [StreamEvent::Started] is reported, followed by [StreamEvent::StreamRestarted] if the
+    /// streams have been started before.
+    pub fn streams_started(&self, streams: StartedStreams) {
+        {
+            let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
+            stats.input_config = streams.input.clone();
+            stats.output_config = Some(streams.output.clone());
+            stats.input_resampler = streams.input_resampler;
+            stats.output_resampler = streams.output_resampler;
+            stats.latency = Some(streams.latency);
+        }
+
+        self.send_event(StreamEvent::Started {
+            input: streams.input,
+            output: streams.output,
+            latency: streams.latency,
+        });
+
+        if self.started_once.swap(true, Ordering::Relaxed) {
+            self.send_event(StreamEvent::StreamRestarted);
+        }
+        self.running.store(true, Ordering::Relaxed);
+    }
+
+    pub fn streams_stopped(&self) {
+        self.running.store(false, Ordering::Relaxed);
+
+        {
+            let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
+            stats.input_config = None;
+            stats.output_config = None;
+            stats.input_resampler = None;
+            stats.output_resampler = None;
+            stats.latency = None;
+        }
+
+        self.send_event(StreamEvent::Stopped);
+    }
+
+    /// Updates the stats with an error that was reported by the stream
+    pub fn report_error(&self, error: &Error) {
+        let xrun = match error {
+            Error::StreamPushUnderflow { channel, frames }
+            | Error::StreamReadUnderflow { channel, frames } => {
+                Some((*channel, XrunKind::Underflow, *frames))
+            }
+            Error::StreamPushOverflow { channel, frames }
+            | Error::StreamReadOverflow { channel, frames } => {
+                Some((*channel, XrunKind::Overflow, *frames))
+            }
+            _ => None,
+        };
+        #[cfg(all(target_os = "linux", feature = "jack"))]
+        let server_xrun = matches!(error, Error::JackXrun);
+        #[cfg(not(all(target_os = "linux", feature = "jack")))]
+        let server_xrun = false;
+
+        let event = {
+            let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
+            stats.last_error = Some(error.to_string());
+
+            match xrun {
+                Some((channel, kind, frames)) => {
+                    let channel_stats = match channel {
+                        StreamChannel::InputToProcessor => &mut stats.input_to_processor,
+                        StreamChannel::ProcessorToOutput => &mut stats.processor_to_output,
+                    };
+                    match kind {
+                        XrunKind::Underflow => channel_stats.underflows += 1,
+                        XrunKind::Overflow => channel_stats.overflows += 1,
+                    }
+
+                    StreamEvent::Xrun {
+                        channel,
+                        kind,
+                        frames,
+                    }
+                }
+                None if server_xrun => {
+                    stats.server_xruns += 1;
+                    StreamEvent::ServerXrun
+                }
+                None => StreamEvent::Error(error.to_string()),
+            }
+        };
+
+        self.send_event(event);
+    }
+
+    /// Updates the stats with an error that prevented the streams from starting
+    pub fn stream_failed(&self, error: &Error) {
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .last_error = Some(error.to_string());
+
+        self.send_event(StreamEvent::StreamFailed(error.to_string()));
+    }
+
+    pub fn send_event(&self, event: StreamEvent) {
+        // Events are dropped if the receiver isn't keeping up
+        self.events.try_send(event).ok();
+    }
+}
+
+fn load_seconds(seconds: &AtomicU64) -> Duration {
+    Duration::from_secs_f64(f64::from_bits(seconds.load(Ordering::Relaxed)).max(0.0))
+}
@@ -0,0 +1,317 @@
+use super::{
+    CallbackInput, CallbackProcessor, CallbackProcessorState, DeviceConfig, Error,
+    FromProcessorReceiver, FromProcessorSender, InputSource, InputSourceControl, MAX_CHANNELS,
+    ProcessorConfig, ProcessorTaps, Recorder, Result, SharedStreamState, StartedStreams,
+    StreamEvent, StreamStats, ToProcessorReceiver, ToProcessorSender,
+};
+use crate::{ProcessorLoad, ProcessorSampleRate, StreamConfig};
+use audio_module::AudioModule;
+use crossbeam_channel::{Receiver, Sender, bounded};
+use rtrb::{Producer, RingBuffer};
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+/// The sample rate that's used when the config follows the output device's sample rate
+const DEFAULT_SAMPLE_RATE: usize = 44100;
+
+/// A stream backend without audio devices, driven manually for testing
+///
+/// The backend provides the same `to_processor`, `from_processor`, and `sample_rate` methods as
+/// `AudioStream`, but audio is only processed when [TestBackend::advance] is called. Input is
+/// injected with [TestBackend::inject_input], and the processor's output is captured until it's
+/// taken with [TestBackend::take_output]. Processing happens in blocks of the config's
+/// `frames_per_update`, so timing is deterministic regardless of the machine's speed.
+///
+/// The processor is run in the same way as an `AudioStream` in `ProcessingMode::Callback` mode,
+/// with each call to `advance` acting as an output stream's callback, and with restarts handing
+/// the processor over to a new callback.
+pub struct TestBackend<M: AudioModule> {
+    to_processor_sender: ToProcessorSender,
+    from_processor_receiver: FromProcessorReceiver<M::Processor>,
+    config: ProcessorConfig,
+    state: CallbackProcessorState<M::Processor>,
+    // The processor's current callback, replaced when the backend is restarted
+    callback: Option<CallbackProcessor<M::Processor>>,
+    // Injected input is passed to the callback a block at a time
+    input: VecDeque<f32>,
+    // `None` for output-only backends
+    input_producer: Option<Producer<f32>>,
+    input_source: InputSourceControl,
+    output: Vec<f32>,
+    buffer: Vec<f32>,
+    position: usize,
+    shared: Arc<SharedStreamState>,
+    events: Receiver<StreamEvent>,
+    error_sender: Sender<Error>,
+    errors: Receiver<Error>,
+}
+
+impl<M: AudioModule> TestBackend<M> {
+    /// Creates a backend using the given config's sample rate, block size, channel count and
+    /// output routing
+    ///
+    /// The backend starts in the running state, with a `StreamEvent::Started` event reported.
+    pub fn new(config: StreamConfig) -> Result<Self> {
+        if config.channels == 0 || config.channels > MAX_CHANNELS {
+            return Err(Error::InvalidStreamConfig {
+                reason: "the channel count must be between 1 and MAX_CHANNELS",
+            });
+        }
+        if config.frames_per_update == 0 {
+            return Err(Error::InvalidStreamConfig {
+                reason: "at least one frame must be processed per update",
+            });
+        }
+
+        let sample_rate = match config.sample_rate {
+            ProcessorSampleRate::Fixed(sample_rate) => sample_rate,
+            ProcessorSampleRate::FollowOutput => DEFAULT_SAMPLE_RATE,
+        };
+        let config = ProcessorConfig {
+            sample_rate,
+            frames_per_update: config.frames_per_update,
+            channels: config.channels,
+            output_only: config.output_only || !M::REQUIRES_INPUT,
+            resampler: config.resampler,
+            routing: config.routing,
+        };
+
+        let channel_capacity = 1024;
+
+        let (sender, receiver) = bounded(channel_capacity);
+        let to_processor_sender = ToProcessorSender::new(sender);
+        let to_processor_receiver = ToProcessorReceiver::new(receiver);
+
+        let (sender, receiver) = bounded(channel_capacity);
+        let from_processor_sender = FromProcessorSender::new(sender);
+        let from_processor_receiver = FromProcessorReceiver::new(receiver);
+
+        let (error_sender, error_receiver) = bounded(channel_capacity);
+        let (event_sender, event_receiver) = bounded(channel_capacity);
+        let shared = Arc::new(SharedStreamState::new(event_sender));
+
+        // Recordings aren't supported, so the recorder is dropped and its tap stays idle
+        let (_, recording_tap) = Recorder::new(
+            sample_rate,
+            config.frames_per_update,
+            config.channels,
+            config.output_only,
+            shared.clone(),
+        );
+        let (input_source, input_source_player) =
+            InputSourceControl::new(sample_rate, config.channels);
+        let taps = ProcessorTaps {
+            recording: recording_tap,
+            input_source: input_source_player,
+        };
+
+        // The processor is always returned by the previous callback, so there's no need to wait
+        let state = CallbackProcessorState::new(
+            M::create_processor,
+            sample_rate,
+            config.channels,
+            to_processor_receiver,
+            from_processor_sender,
+            taps,
+            error_sender.clone(),
+            shared.clone(),
+        )
+        .with_return_timeout(Duration::ZERO);
+
+        let mut backend = Self {
+            to_processor_sender,
+            from_processor_receiver,
+            buffer: vec![0.0; config.frames_per_update * config.channels],
+            config,
+            state,
+            callback: None,
+            input: VecDeque::new(),
+            input_producer: None,
+            input_source,
+            output: Vec::new(),
+            position: 0,
+            shared,
+            events: event_receiver,
+            error_sender,
+            errors: error_receiver,
+        };
+
+        backend.start()?;
+
+        Ok(backend)
+    }
+
+    pub fn to_processor(&self) -> ToProcessorSender {
+        self.to_processor_sender.clone()
+    }
+
+    pub fn from_processor(&self) -> FromProcessorReceiver<M::Processor> {
+        self.from_processor_receiver.clone()
+    }
+
+    pub fn sample_rate(&self) -> usize {
+        self.config.sample_rate
+    }
+
+    pub fn channels(&self) -> usize {
+        self.config.channels
+    }
+
+    /// The number of frames that have been processed so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns a snapshot of the processor's load, see `AudioStream::processor_load`
+    pub fn processor_load(&self) -> ProcessorLoad {
+        self.shared.load_meter().snapshot()
+    }
+
+    /// Returns a snapshot of the backend's stats, see `AudioStream::stats`
+    pub fn stats(&self) -> StreamStats {
+        self.shared.stats()
+    }
+
+    /// Returns a receiver for the backend's events, see `AudioStream::events`
+    pub fn events(&self) -> Receiver<StreamEvent> {
+        self.events.clone()
+    }
+
+    /// Sets where the audio that's passed to the processor comes from, see
+    /// `AudioStream::set_input_source`
+    ///
+    /// The source replaces the injected input once it's picked up by the next processed block.
+    pub fn set_input_source(&self, source: InputSource) -> Result<()> {
+        self.input_source.set(source)
+    }
+
+    /// Queues interleaved input that will be passed to the processor by [TestBackend::advance]
+    ///
+    /// Input is ignored by output-only backends, which process silence.
+    pub fn inject_input(&mut self, samples: &[f32]) {
+        if !self.config.output_only {
+            self.input.extend(samples);
+        }
+    }
+
+    /// The number of injected frames that haven't been processed yet
+    pub fn pending_input_frames(&self) -> usize {
+        self.input.len() / self.config.channels
+    }
+
+    /// Processes the given number of frames
+    ///
+    /// Injected input is passed to the processor, and silence is used once the input runs out.
+    /// Audio is processed in blocks of up to `frames_per_update` frames, with any messages that
+    /// have been sent to the processor being received at the start of the next block.
+    pub fn advance(&mut self, frames: usize) {
+        let channels = self.config.channels;
+
+        let mut remaining = frames;
+        while remaining > 0 {
+            let block_frames = remaining.min(self.config.frames_per_update);
+            let buffer = &mut self.buffer[..block_frames * channels];
+
+            // Pass the block's input to the callback, which uses silence for any missing samples
+            if let Some(input_producer) = &mut self.input_producer {
+                for _ in 0..buffer.len() {
+                    let Some(sample) = self.input.pop_front() else {
+                        break;
+                    };
+                    input_producer.push(sample).ok();
+                }
+            }
+
+            if let Some(callback) = &mut self.callback {
+                callback.process(buffer);
+            } else {
+                buffer.fill(0.0);
+            }
+
+            self.output.extend_from_slice(buffer);
+            self.position += block_frames;
+            remaining -= block_frames;
+        }
+
+        // Errors are reported in the same way as the stream manager
+        while let Ok(error) = self.errors.try_recv() {
+            self.shared.report_error(&error);
+        }
+    }
+
+    /// Returns the interleaved output that has been captured since the last call to
+    /// [TestBackend::take_output]
+    pub fn output(&self) -> &[f32] {
+        &self.output
+    }
+
+    /// Takes the interleaved output that has been captured so far
+    pub fn take_output(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.output)
+    }
+
+    /// Restarts the backend's streams, e.g. as if the device config had changed
+    ///
+    /// As with `AudioStream`, the current callback is dropped, returning the processor so that it
+    /// can be handed over to the new callback, and any injected input that was buffered for the
+    /// old callback is discarded. `StreamEvent::Stopped`, `StreamEvent::Started` and
+    /// `StreamEvent::StreamRestarted` events are reported.
+    pub fn restart(&mut self) -> Result<()> {
+        self.stop();
+        self.input.clear();
+        self.start()
+    }
+
+    /// Restarts the backend's streams with the processor being lost along with the old callback
+    ///
+    /// This simulates an output stream that fails to return the processor during a restart, in
+    /// which case a new processor is created, see `ProcessingMode::Callback`.
+    pub fn recreate_processor(&mut self) -> Result<()> {
+        if let Some(callback) = &mut self.callback {
+            callback.discard_processor();
+        }
+        self.restart()
+    }
+
+    fn start(&mut self) -> Result<()> {
+        let channels = self.config.channels;
+
+        let input = if self.config.output_only {
+            CallbackInput::None
+        } else {
+            let (producer, consumer) = RingBuffer::new(self.config.frames_per_update * channels);
+            self.input_producer = Some(producer);
+            CallbackInput::Injected(consumer)
+        };
+
+        self.callback = Some(CallbackProcessor::new(
+            &mut self.state,
+            &self.config,
+            channels,
+            self.error_sender.clone(),
+            input,
+        )?);
+
+        let device_config = DeviceConfig {
+            device_name: "test".to_string(),
+            channels: channels as u16,
+            sample_rate: self.config.sample_rate as u32,
+            buffer_size: self.config.frames_per_update as u32,
+        };
+        self.shared.streams_started(StartedStreams {
+            input: (!self.config.output_only).then(|| device_config.clone()),
+            output: device_config,
+            input_resampler: None,
+            output_resampler: None,
+            latency: Duration::from_secs_f64(self.config.update_duration()),
+        });
+
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        // Dropping the callback returns the processor to the state
+        if self.callback.take().is_some() {
+            self.shared.streams_stopped();
+        }
+    }
+}
@@ -1,212 +1,550 @@
+mod shared_ring;
+mod worklet;
+
+pub use worklet::{__macro_support, MESSAGE_VERSION, WorkletProcessor};
+
+use crate::{ProcessorLoad, StreamConfig};
 use audio_module::{AudioModule, AudioProcessor, PopMessage, PushMessage, ToProcessor};
 use crossbeam_channel::Receiver;
 use js_sys::{Array, Object, Reflect, Uint8Array};
 use serde::Deserialize;
-use std::{cell::RefCell, marker::PhantomData};
+use shared_ring::{SharedRing, shared_memory_available};
+use std::{
+    cell::{Cell, RefCell},
+    marker::PhantomData,
+    rc::Rc,
+};
 use thiserror::Error;
 use wasm_bindgen::{JsValue, prelude::*};
 use wasm_bindgen_futures::{JsFuture, spawn_local};
 use web_sys::{
-    AudioContext, AudioWorkletNode, AudioWorkletNodeOptions, MediaStream, MediaStreamConstraints,
-    Request, RequestInit, RequestMode, Response, console, window,
+    AudioContext, AudioContextState, AudioWorkletNode, AudioWorkletNodeOptions, MediaDeviceInfo,
+    MediaDeviceKind, MediaStream, MediaStreamAudioSourceNode, MediaStreamConstraints, Request,
+    RequestInit, RequestMode, Response, Window, console, window,
 };
 
 pub const FRAMES_PER_BUFFER: usize = 128;
 
+/// The capacity in bytes of the shared memory ring for messages to the processor
+const TO_PROCESSOR_RING_CAPACITY: u32 = 16 * 1024;
+/// The capacity in bytes of the shared memory ring for messages from the processor
+const FROM_PROCESSOR_RING_CAPACITY: u32 = 256 * 1024;
+/// The capacity in bytes of the shared memory ring for reports of the processor's load
+const LOAD_RING_CAPACITY: u32 = 1024;
+
 thread_local! {
-    static AUDIO_STATE: RefCell<Option<AudioState>> = RefCell::new(None);
+    // The handle that's set up by `initialize_audio`, used by `AudioStream::new`
+    static DEFAULT_HANDLE: RefCell<Option<AudioHandle>> = const { RefCell::new(None) };
+}
+
+/// Options for [AudioEngine::init] and [initialize_audio]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioOptions {
+    /// The id of the input device that should be used, see [available_input_devices]
+    ///
+    /// When `None`, the browser's default input device is used.
+    pub input_device_id: Option<String>,
+    /// Disables the browser's echo cancellation, noise suppression, and automatic gain control
+    pub disable_input_processing: bool,
+    /// The number of channels that should be requested from the input device
+    ///
+    /// When `None`, the browser chooses the channel count.
+    pub input_channels: Option<u32>,
+    /// Runs without an input device, with the processor receiving silent buffers
+    ///
+    /// The user isn't asked for microphone permission in this mode.
+    pub output_only: bool,
+}
+
+impl Default for AudioOptions {
+    fn default() -> Self {
+        Self {
+            input_device_id: None,
+            disable_input_processing: true,
+            input_channels: None,
+            output_only: false,
+        }
+    }
+}
+
+/// An audio input device that's available to the browser, see [available_input_devices]
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputDeviceInfo {
+    /// The device's id, for use with [AudioOptions::input_device_id]
+    pub device_id: String,
+    /// The device's label
+    ///
+    /// Browsers only provide labels after the user has granted microphone permission, so the
+    /// label might be empty.
+    pub label: String,
+}
+
+/// Returns the audio input devices that are available to the browser
+pub async fn available_input_devices() -> Result<Vec<InputDeviceInfo>> {
+    let window = window().ok_or(Error::WindowUnavailable)?;
+    let devices: Array = JsFuture::from(window.navigator().media_devices()?.enumerate_devices()?)
+        .await?
+        .dyn_into()?;
+
+    Ok(devices
+        .iter()
+        .filter_map(|device| device.dyn_into::<MediaDeviceInfo>().ok())
+        .filter(|device| device.kind() == MediaDeviceKind::Audioinput)
+        .map(|device| InputDeviceInfo {
+            device_id: device.device_id(),
+            label: device.label(),
+        })
+        .collect())
 }
 
+/// The browser's audio context, along with the input that's shared by its worklet nodes
+///
+/// Any number of modules can be run concurrently in the engine, each in its own worklet node,
+/// see [AudioEngine::create_handle].
 #[derive(Clone)]
-struct AudioState {
+pub struct AudioEngine {
+    state: Rc<EngineState>,
+}
+
+struct EngineState {
     audio_context: AudioContext,
+    // `None` for output-only engines
+    input: Option<MediaStreamAudioSourceNode>,
+    // The number of streams that are using the engine, the context is suspended when none remain
+    active_streams: Cell<usize>,
+}
+
+impl AudioEngine {
+    /// Sets up the audio context and loads the audio worklet
+    ///
+    /// `worklet_js_path` should be the path to a copy of `./wasm/audio_stream_worklet.js`.
+    /// The input device and its settings are chosen with the [AudioOptions].
+    ///
+    /// The audio context starts suspended, see [AudioStream::start].
+    pub async fn init(worklet_js_path: &str, options: AudioOptions) -> Result<Self> {
+        let window = window().ok_or(Error::WindowUnavailable)?;
+
+        // Create the audio context and add the audio worklet module
+        let audio_context = AudioContext::new()?;
+        let add_module = audio_context.audio_worklet()?.add_module(worklet_js_path)?;
+        JsFuture::from(add_module)
+            .await
+            .map_err(|error| Error::WorkletLoadFailed(js_error_message(&error)))?;
+
+        // Get access to the mic, unless the engine is output-only
+        let input = if options.output_only {
+            None
+        } else {
+            let constraints = MediaStreamConstraints::new();
+            constraints.set_audio(&audio_constraints(&options)?);
+            constraints.set_video(&JsValue::FALSE);
+
+            let stream_promise = window
+                .navigator()
+                .media_devices()?
+                .get_user_media_with_constraints(&constraints)?;
+            let stream: MediaStream = JsFuture::from(stream_promise)
+                .await
+                .map_err(|error| user_media_error(&error))?
+                .dyn_into()?;
+
+            Some(audio_context.create_media_stream_source(&stream)?)
+        };
+
+        // Suspend the audio context now that it's initialized, wait for the user to enable it
+        JsFuture::from(audio_context.suspend()?).await?;
+
+        Ok(Self {
+            state: Rc::new(EngineState {
+                audio_context,
+                input,
+                active_streams: Cell::new(0),
+            }),
+        })
+    }
+
+    pub fn sample_rate(&self) -> usize {
+        self.state.audio_context.sample_rate() as usize
+    }
+
+    /// Creates a worklet node that runs a module's processor
+    ///
+    /// `wasm_path` and `wasm_glue_path` should be the paths to a wasm module along with its glue
+    /// `.js` that will be passed into the `AudioWorkletNode`. The wasm should export a processor
+    /// using [export_wasm_processor](crate::export_wasm_processor).
+    ///
+    /// The node is connected to the engine's input and to the audio context's destination.
+    /// When the page is cross-origin isolated, messages to and from the processor are passed
+    /// through lock-free rings in shared memory, otherwise they're posted to the worklet's
+    /// message port.
+    pub async fn create_handle(
+        &self,
+        wasm_path: &str,
+        wasm_glue_path: &str,
+    ) -> Result<AudioHandle> {
+        let window = window().ok_or(Error::WindowUnavailable)?;
+        let audio_context = &self.state.audio_context;
+
+        // Fetch the audio worklet `.wasm`, and place it in a buffer that can be passed to the worklet
+        let response = fetch(&window, wasm_path).await?;
+        let wasm_buffer = Uint8Array::new(
+            &JsFuture::from(response.array_buffer()?)
+                .await
+                .map_err(|error| fetch_error(wasm_path, &error))?,
+        );
+
+        // Fetch the wasm's glue code
+        let response = fetch(&window, wasm_glue_path).await?;
+        let glue_js = JsFuture::from(response.text()?)
+            .await
+            .map_err(|error| fetch_error(wasm_glue_path, &error))?;
+
+        // Create the processor worklet, passing the wasm buffer and sample rate to the constructor
+        let node_options = AudioWorkletNodeOptions::new();
+
+        let channel_count = Array::new();
+        channel_count.push(&JsValue::from(2));
+        node_options.set_output_channel_count(&channel_count);
+
+        let processor_options = Object::new();
+        Reflect::set(
+            &processor_options,
+            &JsValue::from_str("wasmBuffer"),
+            &wasm_buffer.buffer(),
+        )?;
+        Reflect::set(&processor_options, &JsValue::from_str("wasmGlue"), &glue_js)?;
+        Reflect::set(
+            &processor_options,
+            &JsValue::from_str("sampleRate"),
+            &JsValue::from(audio_context.sample_rate()),
+        )?;
+
+        // Share the message rings with the worklet if shared memory is available
+        let (to_processor_ring, from_processor_ring, load_ring) = if shared_memory_available() {
+            let to_processor_ring = SharedRing::new(TO_PROCESSOR_RING_CAPACITY);
+            let from_processor_ring = SharedRing::new(FROM_PROCESSOR_RING_CAPACITY);
+            let load_ring = SharedRing::new(LOAD_RING_CAPACITY);
+            Reflect::set(
+                &processor_options,
+                &JsValue::from_str("toProcessorRing"),
+                to_processor_ring.buffer(),
+            )?;
+            Reflect::set(
+                &processor_options,
+                &JsValue::from_str("fromProcessorRing"),
+                from_processor_ring.buffer(),
+            )?;
+            Reflect::set(
+                &processor_options,
+                &JsValue::from_str("loadRing"),
+                load_ring.buffer(),
+            )?;
+            (
+                Some(to_processor_ring),
+                Some(from_processor_ring),
+                Some(load_ring),
+            )
+        } else {
+            console::log_1(
+                &"Shared memory is unavailable, messages will be posted to the worklet".into(),
+            );
+            (None, None, None)
+        };
+
+        node_options.set_processor_options(Some(&processor_options));
+
+        let processor_node =
+            AudioWorkletNode::new_with_options(audio_context, "AudioStreamWorklet", &node_options)
+                .map_err(|error| Error::WorkletLoadFailed(js_error_message(&error)))?;
+
+        // Connect the nodes: mic -> worklet -> destination
+        if let Some(input) = &self.state.input {
+            input.connect_with_audio_node(&processor_node)?;
+        }
+        processor_node.connect_with_audio_node(&audio_context.destination())?;
+
+        let (message_sender, message_receiver) = crossbeam_channel::bounded(1024);
+        let processor_load = Rc::new(Cell::new(ProcessorLoad::default()));
+
+        // Move producer into the message handler closure
+        let onmessage = Closure::wrap(Box::new({
+            let processor_load = processor_load.clone();
+            move |event: web_sys::MessageEvent| {
+                let data = event.data();
+
+                // Load reports are kept separately from the processor's messages
+                if Reflect::has(&data, &worklet::LOAD_REPORT_KEY.into()).unwrap_or(false) {
+                    match serde_wasm_bindgen::from_value::<worklet::LoadReport>(data) {
+                        Ok(report) => processor_load.set(report.processor_load),
+                        Err(error) => console::error_1(&error.to_string().into()),
+                    }
+                    return;
+                }
+
+                if message_sender.try_send(data).is_err() {
+                    console::warn_1(&"Ring buffer full, dropping message".into());
+                }
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        processor_node
+            .port()?
+            .set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+
+        onmessage.forget();
+
+        Ok(AudioHandle {
+            engine: self.clone(),
+            processor_node,
+            message_receiver,
+            to_processor_ring,
+            from_processor_ring,
+            load_ring,
+            processor_load,
+        })
+    }
+
+    /// Resumes the audio context
+    ///
+    /// Browsers only allow audio to start following a user gesture, if the context remains
+    /// suspended then [Error::ContextSuspended] is returned.
+    pub async fn resume(&self) -> Result<()> {
+        let audio_context = &self.state.audio_context;
+        JsFuture::from(audio_context.resume()?).await?;
+
+        if audio_context.state() == AudioContextState::Running {
+            Ok(())
+        } else {
+            Err(Error::ContextSuspended)
+        }
+    }
+
+    /// Suspends the audio context
+    pub async fn suspend(&self) -> Result<()> {
+        JsFuture::from(self.state.audio_context.suspend()?).await?;
+        Ok(())
+    }
+}
+
+/// A worklet node that runs a module's processor, see [AudioEngine::create_handle]
+#[derive(Clone)]
+pub struct AudioHandle {
+    engine: AudioEngine,
     processor_node: AudioWorkletNode,
-    sample_rate: usize,
     message_receiver: Receiver<JsValue>,
+    // Shared memory rings for messages, `None` when the page isn't cross-origin isolated
+    to_processor_ring: Option<SharedRing>,
+    from_processor_ring: Option<SharedRing>,
+    load_ring: Option<SharedRing>,
+    // The most recent load report from the processor
+    processor_load: Rc<Cell<ProcessorLoad>>,
+}
+
+impl AudioHandle {
+    pub fn engine(&self) -> &AudioEngine {
+        &self.engine
+    }
+
+    /// Returns the most recent snapshot of the processor's load
+    ///
+    /// The worklet reports its load periodically, so the snapshot can lag behind by a few blocks.
+    pub fn processor_load(&self) -> ProcessorLoad {
+        if let Some(ring) = &self.load_ring {
+            let mut report = Vec::new();
+            while ring.pop(&mut report) {
+                match postcard::from_bytes(&report) {
+                    Ok(processor_load) => self.processor_load.set(processor_load),
+                    Err(error) => console::error_1(&error.to_string().into()),
+                }
+            }
+        }
+
+        self.processor_load.get()
+    }
 }
 
-/// Sets up the audio context with an `AudioWorkletNode`
+/// Sets up an [AudioEngine] with a single module, for use with [AudioStream::new]
 ///
-/// `worklet_js_path` should be the path to a copy of `./wasm/audio_stream_worklet.js`.
-///   - This contains the `AudioWorkletNode` that will process input from the microphone.
-/// `wasm_path` and `wasm_glue_path` should be the paths to a wasm module along with its glue `.js`
-///   that will be passed into the `AudioWorkletNode`.
-///   - The following functions should be exported from the wasm:
-///     - `create_processor(sample_rate: usize) -> *mut Processor`:
-///       - Creates an instance of a processor.
-///     - `set_parameter(*mut Processor, id: usize, value: f32)`:
-///       - Sets a parameter by id.
-///     - `process(*mut Processor,
-///                input_l: *const f32, input_r: *const f32, output_l: *mut f32, output_r: *mut f32,
-///                sample_count: usize)`:
-///       - Processes frames provided in deinterleaved buffers.
-///     - `createBuffer(size: usize) -> *mut f32`:
-///       - Creates a buffer for use by the process function.
+/// See [AudioEngine::init] and [AudioEngine::create_handle] for details of the arguments.
 pub async fn initialize_audio(
     wasm_path: &str,
     wasm_glue_path: &str,
     worklet_js_path: &str,
-) -> std::result::Result<(), JsValue> {
-    let window = window().ok_or("missing global window")?;
+    options: AudioOptions,
+) -> Result<()> {
+    let engine = AudioEngine::init(worklet_js_path, options).await?;
+    let handle = engine.create_handle(wasm_path, wasm_glue_path).await?;
+
+    DEFAULT_HANDLE.with(|default_handle| *default_handle.borrow_mut() = Some(handle));
+
+    Ok(())
+}
+
+/// Returns the `getUserMedia` audio constraints for the given options
+fn audio_constraints(options: &AudioOptions) -> Result<JsValue> {
+    let constraints = Object::new();
+
+    if let Some(device_id) = &options.input_device_id {
+        let exact = Object::new();
+        Reflect::set(&exact, &"exact".into(), &device_id.into())?;
+        Reflect::set(&constraints, &"deviceId".into(), &exact)?;
+    }
+
+    if options.disable_input_processing {
+        for constraint in ["echoCancellation", "noiseSuppression", "autoGainControl"] {
+            Reflect::set(&constraints, &constraint.into(), &JsValue::FALSE)?;
+        }
+    }
+
+    if let Some(channels) = options.input_channels {
+        Reflect::set(&constraints, &"channelCount".into(), &channels.into())?;
+    }
+
+    Ok(constraints.into())
+}
 
-    // Fetch the audio worklet `.wasm`
+/// Fetches a file, treating unsuccessful responses as errors
+async fn fetch(window: &Window, path: &str) -> Result<Response> {
     let fetch_options = RequestInit::new();
     fetch_options.set_method("GET");
     fetch_options.set_mode(RequestMode::Cors);
-    let request = Request::new_with_str_and_init(&wasm_path, &fetch_options)?;
-    let response: Response = JsFuture::from(window.fetch_with_request(&request))
-        .await?
-        .dyn_into()?;
-    // Place the fetched wasm in a buffer that can be passed to the worklet
-    let wasm_buffer = Uint8Array::new(&JsFuture::from(response.array_buffer()?).await?);
+    let request = Request::new_with_str_and_init(path, &fetch_options)?;
 
-    // Fetch the wasm's glue code
-    let request = Request::new_with_str_and_init(&wasm_glue_path, &fetch_options)?;
     let response: Response = JsFuture::from(window.fetch_with_request(&request))
-        .await?
+        .await
+        .map_err(|error| fetch_error(path, &error))?
         .dyn_into()?;
-    let glue_js = JsFuture::from(response.text()?).await?;
-
-    // Create the audio context and add the audio worklet module
-    let audio_context = AudioContext::new()?;
-    JsFuture::from(
-        audio_context
-            .audio_worklet()?
-            .add_module(&worklet_js_path)?,
-    )
-    .await?;
-
-    // Get access to the mic
-    let constraints = MediaStreamConstraints::new();
-    constraints.set_audio(&JsValue::TRUE);
-    constraints.set_video(&JsValue::FALSE);
-
-    let stream_promise = window
-        .navigator()
-        .media_devices()?
-        .get_user_media_with_constraints(&constraints)?;
-    let stream: MediaStream = JsFuture::from(stream_promise).await?.dyn_into()?;
-
-    let mic = audio_context.create_media_stream_source(&stream)?;
-
-    // Create the processor worklet, passing the wasm buffer and sample rate to the constructor
-    let node_options = AudioWorkletNodeOptions::new();
-
-    let channel_count = Array::new();
-    channel_count.push(&JsValue::from(2));
-    node_options.set_output_channel_count(&channel_count);
-
-    let sample_rate = audio_context.sample_rate();
-    let processor_options = Object::new();
-    Reflect::set(
-        &processor_options,
-        &JsValue::from_str("wasmBuffer"),
-        &wasm_buffer.buffer(),
-    )?;
-    Reflect::set(&processor_options, &JsValue::from_str("wasmGlue"), &glue_js)?;
-    Reflect::set(
-        &processor_options,
-        &JsValue::from_str("sampleRate"),
-        &JsValue::from(sample_rate),
-    )?;
-    node_options.set_processor_options(Some(&processor_options));
-
-    let processor_node =
-        AudioWorkletNode::new_with_options(&audio_context, "AudioStreamWorklet", &node_options)?;
-
-    // Connect the nodes: mic -> worklet -> destination
-    mic.connect_with_audio_node(&processor_node)?;
-    processor_node.connect_with_audio_node(&audio_context.destination())?;
-
-    // Suspend the audio context now that it's initialized, wait for the user to enable it
-    JsFuture::from(audio_context.suspend()?).await?;
-
-    let (message_sender, message_receiver) = crossbeam_channel::bounded(1024);
-
-    // Move producer into the message handler closure
-    let onmessage = Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
-        let data = event.data();
-
-        if message_sender.try_send(data).is_err() {
-            console::warn_1(&"Ring buffer full, dropping message".into());
-        }
-    }) as Box<dyn FnMut(_)>);
 
-    processor_node
-        .port()?
-        .set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+    if response.ok() {
+        Ok(response)
+    } else {
+        Err(Error::WasmFetchFailed {
+            path: path.to_string(),
+            reason: format!("HTTP status {}", response.status()),
+        })
+    }
+}
 
-    onmessage.forget();
+fn fetch_error(path: &str, error: &JsValue) -> Error {
+    Error::WasmFetchFailed {
+        path: path.to_string(),
+        reason: js_error_message(error),
+    }
+}
 
-    AUDIO_STATE.with(|state| {
-        *state.borrow_mut() = Some(AudioState {
-            audio_context,
-            processor_node,
-            sample_rate: sample_rate as usize,
-            message_receiver,
-        })
-    });
+/// Converts a `getUserMedia` failure into an [Error]
+fn user_media_error(error: &JsValue) -> Error {
+    let name = Reflect::get(error, &"name".into())
+        .ok()
+        .and_then(|name| name.as_string())
+        .unwrap_or_default();
+
+    match name.as_str() {
+        "NotAllowedError" | "SecurityError" => Error::PermissionDenied,
+        "NotFoundError" | "NotReadableError" | "OverconstrainedError" => {
+            Error::InputUnavailable(js_error_message(error))
+        }
+        _ => Error::Js(js_error_message(error)),
+    }
+}
 
-    Ok(())
+/// Returns the message of a JS error, falling back to its debug representation
+fn js_error_message(error: &JsValue) -> String {
+    Reflect::get(error, &"message".into())
+        .ok()
+        .and_then(|message| message.as_string())
+        .or_else(|| error.as_string())
+        .unwrap_or_else(|| format!("{error:?}"))
 }
 
-#[derive(Clone)]
 pub struct AudioStream<M> {
-    state: AudioState,
+    handle: AudioHandle,
     _module: PhantomData<M>,
 }
 
 impl<M: AudioModule> AudioStream<M> {
-    pub fn new() -> Result<Self> {
-        let Some(state) = AUDIO_STATE.with(|ctx| ctx.borrow().clone()) else {
+    /// Creates a stream using the handle set up by [initialize_audio]
+    ///
+    /// The audio context is resumed in the background, with any errors logged to the console.
+    /// Use [AudioStream::with_handle] and [AudioStream::start] to handle errors when starting.
+    ///
+    /// The processor runs in the audio context's worklet, so its sample rate, block size, and
+    /// channel count are determined by the browser and the config is currently ignored.
+    pub fn new(config: StreamConfig) -> Result<Self> {
+        let Some(handle) = DEFAULT_HANDLE.with(|handle| handle.borrow().clone()) else {
             return Err(Error::AudioUninitialized);
         };
 
-        let audio_context = state.audio_context.clone();
+        let stream = Self::with_handle(&handle, config)?;
+
+        let engine = handle.engine.clone();
         spawn_local(async move {
-            match audio_context.resume() {
-                Ok(promise) => match JsFuture::from(promise).await {
-                    Ok(_) => console::log_1(&"Audio context resumed".into()),
-                    Err(error) => console::error_1(&error),
-                },
-                Err(error) => console::error_1(&error),
+            match engine.resume().await {
+                Ok(_) => console::log_1(&"Audio context resumed".into()),
+                Err(error) => console::error_1(&error.to_string().into()),
             }
         });
 
+        Ok(stream)
+    }
+
+    /// Creates a stream for a handle's worklet node
+    ///
+    /// The audio context isn't resumed until [AudioStream::start] is called.
+    pub fn with_handle(handle: &AudioHandle, _config: StreamConfig) -> Result<Self> {
+        let active_streams = &handle.engine.state.active_streams;
+        active_streams.set(active_streams.get() + 1);
+
         Ok(Self {
-            state,
+            handle: handle.clone(),
             _module: PhantomData,
         })
     }
 
+    /// Starts the stream by resuming the audio context, see [AudioEngine::resume]
+    pub async fn start(&self) -> Result<()> {
+        self.handle.engine.resume().await
+    }
+
     pub fn to_processor(&self) -> ToProcessorSender {
         ToProcessorSender {
-            processor_node: self.state.processor_node.clone(),
+            processor_node: self.handle.processor_node.clone(),
+            ring: self.handle.to_processor_ring.clone(),
         }
     }
 
     pub fn from_processor(&self) -> FromProcessorReceiver<M::Processor> {
         FromProcessorReceiver {
-            message_receiver: self.state.message_receiver.clone(),
+            message_receiver: self.handle.message_receiver.clone(),
+            ring: self.handle.from_processor_ring.clone(),
             _processor: PhantomData,
         }
     }
 
     pub fn sample_rate(&self) -> usize {
-        self.state.sample_rate
+        self.handle.engine.sample_rate()
+    }
+
+    /// Returns a snapshot of the processor's load, see [AudioHandle::processor_load]
+    pub fn processor_load(&self) -> ProcessorLoad {
+        self.handle.processor_load()
     }
 }
 
 impl<M> Drop for AudioStream<M> {
     fn drop(&mut self) {
-        let audio_context = self.state.audio_context.clone();
+        // The audio context is suspended once the engine's last stream has been dropped
+        let active_streams = &self.handle.engine.state.active_streams;
+        active_streams.set(active_streams.get().saturating_sub(1));
+        if active_streams.get() > 0 {
+            return;
+        }
+
+        let engine = self.handle.engine.clone();
         spawn_local(async move {
-            match audio_context.suspend() {
-                Ok(promise) => match JsFuture::from(promise).await {
-                    Ok(_) => console::log_1(&"Audio context suspended".into()),
-                    Err(error) => console::error_1(&error),
-                },
-                Err(error) => console::error_1(&error),
+            match engine.suspend().await {
+                Ok(_) => console::log_1(&"Audio context suspended".into()),
+                Err(error) => console::error_1(&error.to_string().into()),
             }
         });
     }
@@ -215,29 +553,42 @@ impl<M> Drop for AudioStream<M> {
 #[derive(Clone, PartialEq)]
 pub struct ToProcessorSender {
     processor_node: AudioWorkletNode,
+    ring: Option<SharedRing>,
 }
 
 impl PushMessage<ToProcessor> for ToProcessorSender {
     fn push(&self, message: ToProcessor) -> bool {
-        match message {
-            ToProcessor::SetParameter(id, value) => match self.processor_node.port() {
-                Ok(port) => {
-                    let message = Object::new();
-                    Reflect::set(&message, &"id".into(), &id.into()).ok();
-                    Reflect::set(&message, &"value".into(), &value.into()).ok();
-                    if let Err(error) = port.post_message(&message) {
-                        console::error_1(&error);
-                        false
-                    } else {
-                        true
-                    }
-                }
+        if let Some(ring) = &self.ring {
+            return match worklet::encode_message_bytes(message) {
+                Ok(message) => ring.push(&message),
                 Err(error) => {
+                    console::error_1(&error.to_string().into());
+                    false
+                }
+            };
+        }
+
+        let message = match worklet::encode_message(message) {
+            Ok(message) => message,
+            Err(error) => {
+                console::error_1(&error.into());
+                return false;
+            }
+        };
+
+        match self.processor_node.port() {
+            Ok(port) => {
+                if let Err(error) = port.post_message(&message) {
                     console::error_1(&error);
                     false
+                } else {
+                    true
                 }
-            },
-            _ => true,
+            }
+            Err(error) => {
+                console::error_1(&error);
+                false
+            }
         }
     }
 }
@@ -245,6 +596,7 @@ impl PushMessage<ToProcessor> for ToProcessorSender {
 #[derive(Clone)]
 pub struct FromProcessorReceiver<P> {
     message_receiver: Receiver<JsValue>,
+    ring: Option<SharedRing>,
     _processor: PhantomData<P>,
 }
 
@@ -254,6 +606,17 @@ where
     P::OutputMessage: for<'de> Deserialize<'de>,
 {
     fn pop(&self) -> Option<P::OutputMessage> {
+        if let Some(ring) = &self.ring {
+            let mut message = Vec::new();
+            while ring.pop(&mut message) {
+                match postcard::from_bytes(&message) {
+                    Ok(result) => return Some(result),
+                    Err(error) => console::error_1(&error.to_string().into()),
+                }
+            }
+            return None;
+        }
+
         if let Ok(message) = self.message_receiver.try_recv() {
             match serde_wasm_bindgen::from_value(message) {
                 Ok(result) => Some(result),
@@ -272,6 +635,32 @@ where
 pub enum Error {
     #[error("Audio is uninitialized (did you call `initialize_audio`?)")]
     AudioUninitialized,
+    #[error("The browser window is unavailable")]
+    WindowUnavailable,
+    #[error("Permission to use the microphone was denied")]
+    PermissionDenied,
+    #[error("The audio input is unavailable: {0}")]
+    InputUnavailable(String),
+    #[error("Failed to load the audio worklet: {0}")]
+    WorkletLoadFailed(String),
+    #[error("Failed to fetch '{path}': {reason}")]
+    WasmFetchFailed { path: String, reason: String },
+    #[error("The audio context is suspended, audio can only be started after a user gesture")]
+    ContextSuspended,
+    #[error("JS error: {0}")]
+    Js(String),
+}
+
+impl From<JsValue> for Error {
+    fn from(error: JsValue) -> Self {
+        Self::Js(js_error_message(&error))
+    }
+}
+
+impl From<Error> for JsValue {
+    fn from(error: Error) -> Self {
+        js_sys::Error::new(&error.to_string()).into()
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -4,7 +4,9 @@ class AudioStreamWorklet extends AudioWorkletProcessor {
   constructor(options) {
     super();
 
-    const { wasmBuffer, wasmGlue, sampleRate } = options.processorOptions;
+    // The message rings are only provided when shared memory is available
+    const { wasmBuffer, wasmGlue, sampleRate, toProcessorRing, fromProcessorRing, loadRing } =
+      options.processorOptions;
 
     // Run the wasm_bindgen setup code, and return the wasm_bindgen object
     const init_wasm_bindgen = new Function(`
@@ -16,15 +18,19 @@ class AudioStreamWorklet extends AudioWorkletProcessor {
     const module = new WebAssembly.Module(wasmBuffer);
     this.wasm = wasm_bindgen.initSync({ module });
 
-    this.processor = new wasm_bindgen.Processor(sampleRate);
+    this.processor = new wasm_bindgen.Processor(
+      sampleRate,
+      toProcessorRing,
+      fromProcessorRing,
+      loadRing,
+    );
 
+    // Messages are encoded by the stream's ToProcessorSender and decoded by the processor
     this.port.onmessage = (e) => {
-      const { id, value } = e.data;
-
       const processor = this.processor;
       if (!processor) return;
 
-      processor.set_parameter(id, value);
+      processor.receive_message(e.data);
     }
   }
 
@@ -38,13 +44,20 @@ class AudioStreamWorklet extends AudioWorkletProcessor {
     const input = inputs[0];
     const output = outputs[0];
 
-    if (input.length == 0 || output.length < 2) {
-      console.error("Missing io (inputs: %i, outputs: %i)", input.length, output.length);
+    if (output.length < 2) {
+      console.error("Missing outputs (outputs: %i)", output.length);
       return false;
     }
 
-    const in_left = input[0];
-    const in_right = (input.length == 1) ? input[0] : input[1];
+    // Output-only streams don't have a connected input, so silence is processed instead
+    if (input.length == 0) {
+      if (!this.silence || this.silence.length != output[0].length) {
+        this.silence = new Float32Array(output[0].length);
+      }
+    }
+
+    const in_left = (input.length == 0) ? this.silence : input[0];
+    const in_right = (input.length < 2) ? in_left : input[1];
 
     processor.process(in_left, in_right, output[0], output[1], (message) => {
       this.port.postMessage(message);
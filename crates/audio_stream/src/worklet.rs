@@ -0,0 +1,393 @@
+use super::SharedRing;
+use crate::{LoadMeter, ProcessorLoad};
+use audio_module::{AudioModule, AudioProcessor, PopMessage, PushMessage, ToProcessor};
+use js_sys::{Date, Float32Array, Function, Reflect, SharedArrayBuffer};
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::VecDeque, time::Duration};
+use wasm_bindgen::{JsCast, JsValue};
+
+/// The version of the encoding that's used for messages that are posted to the worklet
+///
+/// This should be incremented whenever the encoding of [ToProcessor] changes, so that a worklet
+/// that was built against a different version rejects messages instead of misinterpreting them.
+pub const MESSAGE_VERSION: u32 = 1;
+
+/// The maximum encoded size of a message from the processor when shared memory is used
+const OUTPUT_MESSAGE_MAX_BYTES: usize = 16 * 1024;
+
+/// The number of processed blocks between each report of the processor's load
+const LOAD_REPORT_INTERVAL: usize = 64;
+/// The maximum encoded size of a [ProcessorLoad] report when shared memory is used
+const LOAD_REPORT_MAX_BYTES: usize = 64;
+
+/// The property that identifies a [LoadReport] that's been posted to the worklet's port
+pub(crate) const LOAD_REPORT_KEY: &str = "audioStreamProcessorLoad";
+
+/// Items used by [export_wasm_processor], not intended to be used directly
+#[doc(hidden)]
+pub mod __macro_support {
+    pub use js_sys::{Float32Array, Function, SharedArrayBuffer};
+    pub use wasm_bindgen::JsValue;
+}
+
+/// Exports a processor for the given `AudioModule` to `audio_stream_worklet.js`
+///
+/// The macro should be called once in the crate that gets compiled to the worklet's wasm, which
+/// needs to have `wasm-bindgen` as a dependency. The module's `OutputMessage` needs to implement
+/// `serde::Serialize` so that it can be posted from the worklet.
+///
+/// ```ignore
+/// #[cfg(target_arch = "wasm32")]
+/// audio_stream::export_wasm_processor!(MyModule);
+/// ```
+#[macro_export]
+macro_rules! export_wasm_processor {
+    ($module:ty) => {
+        #[::wasm_bindgen::prelude::wasm_bindgen]
+        pub struct Processor($crate::WorkletProcessor<$module>);
+
+        #[::wasm_bindgen::prelude::wasm_bindgen]
+        impl Processor {
+            #[wasm_bindgen(constructor)]
+            pub fn new(
+                sample_rate: usize,
+                to_processor_ring: Option<$crate::__macro_support::SharedArrayBuffer>,
+                from_processor_ring: Option<$crate::__macro_support::SharedArrayBuffer>,
+                load_ring: Option<$crate::__macro_support::SharedArrayBuffer>,
+            ) -> Self {
+                Self($crate::WorkletProcessor::new(
+                    sample_rate,
+                    to_processor_ring,
+                    from_processor_ring,
+                    load_ring,
+                ))
+            }
+
+            pub fn receive_message(&mut self, message: $crate::__macro_support::JsValue) {
+                self.0.receive_message(&message);
+            }
+
+            pub fn process(
+                &mut self,
+                input_l: $crate::__macro_support::Float32Array,
+                input_r: $crate::__macro_support::Float32Array,
+                output_l: $crate::__macro_support::Float32Array,
+                output_r: $crate::__macro_support::Float32Array,
+                on_message: &$crate::__macro_support::Function,
+            ) {
+                self.0
+                    .process(&input_l, &input_r, &output_l, &output_r, on_message);
+            }
+        }
+    };
+}
+
+/// Runs an `AudioModule`'s processor inside the audio worklet, see [export_wasm_processor]
+pub struct WorkletProcessor<M: AudioModule> {
+    processor: M::Processor,
+    to_processor: WorkletMessages<ToProcessor>,
+    from_processor: WorkletMessages<<M::Processor as AudioProcessor>::OutputMessage>,
+    // A buffer for interleaving / deinterleaving the audio worklet's buffers
+    buffer: Vec<f32>,
+    // `None` when shared memory isn't available and messages are posted to the worklet's port
+    rings: Option<WorkletRings>,
+    sample_rate: usize,
+    clock: Clock,
+    load_meter: LoadMeter,
+    blocks_since_load_report: usize,
+}
+
+/// The shared memory rings that messages are passed through, see `SharedRing`
+struct WorkletRings {
+    to_processor: SharedRing,
+    from_processor: SharedRing,
+    // Load reports are passed separately so that they don't mix with the processor's messages
+    load: SharedRing,
+    // A buffer for encoding and decoding messages
+    message_buffer: Vec<u8>,
+}
+
+impl<M> WorkletProcessor<M>
+where
+    M: AudioModule,
+    <M::Processor as AudioProcessor>::OutputMessage: Serialize,
+{
+    /// Creates the processor, using the rings' shared buffers for messages if they're provided
+    pub fn new(
+        sample_rate: usize,
+        to_processor_ring: Option<SharedArrayBuffer>,
+        from_processor_ring: Option<SharedArrayBuffer>,
+        load_ring: Option<SharedArrayBuffer>,
+    ) -> Self {
+        let rings = match (to_processor_ring, from_processor_ring, load_ring) {
+            (Some(to_processor), Some(from_processor), Some(load)) => Some(WorkletRings {
+                to_processor: SharedRing::from_buffer(to_processor),
+                from_processor: SharedRing::from_buffer(from_processor),
+                load: SharedRing::from_buffer(load),
+                message_buffer: vec![0; OUTPUT_MESSAGE_MAX_BYTES],
+            }),
+            _ => None,
+        };
+
+        Self {
+            processor: M::create_processor(sample_rate),
+            to_processor: Default::default(),
+            from_processor: Default::default(),
+            // Sized for the standard render quantum, see `process`
+            buffer: vec![0.0; super::FRAMES_PER_BUFFER * 2],
+            rings,
+            sample_rate,
+            clock: Clock::new(),
+            load_meter: LoadMeter::new(),
+            blocks_since_load_report: 0,
+        }
+    }
+
+    /// Queues a message that was posted to the worklet by `ToProcessorSender`
+    ///
+    /// The message is passed to the processor at the start of the next call to `process`.
+    pub fn receive_message(&mut self, message: &JsValue) {
+        match decode_message(message) {
+            Ok(message) => self.to_processor.messages.borrow_mut().push_back(message),
+            Err(error) => web_sys::console::error_1(&error.into()),
+        }
+    }
+
+    /// Processes the worklet's deinterleaved stereo buffers
+    ///
+    /// Messages from the processor are pushed to the shared memory ring when it's available,
+    /// otherwise they're serialized and passed to `on_message`. The processor's load is measured
+    /// and reported periodically in the same way, see `AudioStream::processor_load`.
+    pub fn process(
+        &mut self,
+        input_l: &Float32Array,
+        input_r: &Float32Array,
+        output_l: &Float32Array,
+        output_r: &Float32Array,
+        on_message: &Function,
+    ) {
+        // The buffer only needs to be resized if the worklet's render quantum size changes
+        let frames = output_l.length() as usize;
+        if self.buffer.len() != frames * 2 {
+            self.buffer.resize(frames * 2, 0.0);
+        }
+
+        if let Some(rings) = &mut self.rings {
+            while rings.to_processor.pop(&mut rings.message_buffer) {
+                match decode_message_bytes(&rings.message_buffer) {
+                    Ok(message) => self.to_processor.messages.borrow_mut().push_back(message),
+                    Err(error) => web_sys::console::error_1(&error.into()),
+                }
+            }
+        }
+
+        // Interleave the input buffers into the process buffer
+        for (i, buffer_frame) in self.buffer.chunks_exact_mut(2).enumerate() {
+            buffer_frame[0] = input_l.get_index(i as u32);
+            buffer_frame[1] = input_r.get_index(i as u32);
+        }
+
+        let processing_start = self.clock.now_ms();
+        self.processor.process_buffer(
+            &mut self.buffer,
+            2,
+            &self.to_processor,
+            &self.from_processor,
+        );
+        let processing_time = (self.clock.now_ms() - processing_start).max(0.0) / 1000.0;
+        self.load_meter.record(
+            Duration::from_secs_f64(processing_time),
+            Duration::from_secs_f64(frames as f64 / self.sample_rate as f64),
+        );
+
+        // Deinterleave the process buffer into the output buffers
+        for (i, buffer_frame) in self.buffer.chunks_exact(2).enumerate() {
+            output_l.set_index(i as u32, buffer_frame[0]);
+            output_r.set_index(i as u32, buffer_frame[1]);
+        }
+
+        let mut messages = self.from_processor.messages.borrow_mut();
+        while let Some(message) = messages.pop_front() {
+            match &mut self.rings {
+                Some(rings) => {
+                    rings.message_buffer.resize(OUTPUT_MESSAGE_MAX_BYTES, 0);
+                    match postcard::to_slice(&message, &mut rings.message_buffer) {
+                        Ok(encoded) => {
+                            if !rings.from_processor.push(encoded) {
+                                web_sys::console::warn_1(
+                                    &"Message ring full, dropping message".into(),
+                                );
+                            }
+                        }
+                        Err(error) => web_sys::console::error_1(
+                            &format!("Failed to encode processor message: {error}").into(),
+                        ),
+                    }
+                }
+                None => {
+                    if let Ok(js_message) = serde_wasm_bindgen::to_value(&message) {
+                        on_message.call1(&JsValue::null(), &js_message).ok();
+                    }
+                }
+            }
+        }
+        drop(messages);
+
+        self.report_load(on_message);
+    }
+
+    /// Passes a snapshot of the processor's load to the main thread every [LOAD_REPORT_INTERVAL]
+    /// blocks
+    fn report_load(&mut self, on_message: &Function) {
+        self.blocks_since_load_report += 1;
+        if self.blocks_since_load_report < LOAD_REPORT_INTERVAL {
+            return;
+        }
+        self.blocks_since_load_report = 0;
+
+        let processor_load = self.load_meter.snapshot();
+        match &self.rings {
+            Some(rings) => {
+                // If the ring is full then the report is dropped, another will follow shortly
+                let mut encoded = [0; LOAD_REPORT_MAX_BYTES];
+                if let Ok(encoded) = postcard::to_slice(&processor_load, &mut encoded) {
+                    rings.load.push(encoded);
+                }
+            }
+            None => {
+                if let Ok(report) = serde_wasm_bindgen::to_value(&LoadReport { processor_load }) {
+                    on_message.call1(&JsValue::null(), &report).ok();
+                }
+            }
+        }
+    }
+}
+
+/// A snapshot of the processor's load, posted to the worklet's port when shared memory isn't
+/// available
+///
+/// The field is renamed to match [LOAD_REPORT_KEY] so that reports can be told apart from the
+/// processor's messages.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LoadReport {
+    #[serde(rename = "audioStreamProcessorLoad")]
+    pub processor_load: ProcessorLoad,
+}
+
+/// Reads the current time for measuring the processor's load
+///
+/// `performance.now()` isn't available in the audio worklet's scope in all browsers, in which
+/// case `Date.now()` is used. Its millisecond resolution is too coarse for individual blocks,
+/// but the average load remains accurate.
+struct Clock {
+    performance: Option<(JsValue, Function)>,
+}
+
+impl Clock {
+    fn new() -> Self {
+        let performance = Reflect::get(&js_sys::global(), &"performance".into())
+            .ok()
+            .filter(|performance| performance.is_object())
+            .and_then(|performance| {
+                let now = Reflect::get(&performance, &"now".into())
+                    .ok()?
+                    .dyn_into::<Function>()
+                    .ok()?;
+                Some((performance, now))
+            });
+
+        Self { performance }
+    }
+
+    /// The current time in milliseconds
+    fn now_ms(&self) -> f64 {
+        self.performance
+            .as_ref()
+            .and_then(|(performance, now)| now.call0(performance).ok())
+            .and_then(|time| time.as_f64())
+            .unwrap_or_else(Date::now)
+    }
+}
+
+/// A [ToProcessor] message along with the version of its encoding
+#[derive(Serialize, Deserialize)]
+struct EncodedMessage {
+    version: u32,
+    message: ToProcessor,
+}
+
+/// The version of an [EncodedMessage], which is checked before the message is decoded
+#[derive(Deserialize)]
+struct EncodedMessageVersion {
+    version: u32,
+}
+
+/// Encodes a message for posting to the worklet, see [MESSAGE_VERSION]
+pub(crate) fn encode_message(message: ToProcessor) -> Result<JsValue, serde_wasm_bindgen::Error> {
+    serde_wasm_bindgen::to_value(&EncodedMessage {
+        version: MESSAGE_VERSION,
+        message,
+    })
+}
+
+/// Encodes a message for pushing to the worklet's shared memory ring, see [MESSAGE_VERSION]
+pub(crate) fn encode_message_bytes(message: ToProcessor) -> postcard::Result<Vec<u8>> {
+    postcard::to_allocvec(&EncodedMessage {
+        version: MESSAGE_VERSION,
+        message,
+    })
+}
+
+fn decode_message_bytes(bytes: &[u8]) -> Result<ToProcessor, String> {
+    let (version, _) = postcard::take_from_bytes::<u32>(bytes)
+        .map_err(|error| format!("Invalid processor message: {error}"))?;
+    if version != MESSAGE_VERSION {
+        return Err(format!(
+            "Unsupported processor message version {version} (expected {MESSAGE_VERSION})"
+        ));
+    }
+
+    postcard::from_bytes::<EncodedMessage>(bytes)
+        .map(|encoded| encoded.message)
+        .map_err(|error| format!("Invalid processor message: {error}"))
+}
+
+fn decode_message(message: &JsValue) -> Result<ToProcessor, String> {
+    let EncodedMessageVersion { version } = serde_wasm_bindgen::from_value(message.clone())
+        .map_err(|error| format!("Invalid processor message: {error}"))?;
+    if version != MESSAGE_VERSION {
+        return Err(format!(
+            "Unsupported processor message version {version} (expected {MESSAGE_VERSION})"
+        ));
+    }
+
+    serde_wasm_bindgen::from_value::<EncodedMessage>(message.clone())
+        .map(|encoded| encoded.message)
+        .map_err(|error| format!("Invalid processor message: {error}"))
+}
+
+/// A message queue that's shared between the worklet and its processor
+struct WorkletMessages<T> {
+    messages: RefCell<VecDeque<T>>,
+}
+
+impl<T> Default for WorkletMessages<T> {
+    fn default() -> Self {
+        Self {
+            messages: RefCell::new(VecDeque::new()),
+        }
+    }
+}
+
+impl<T: Send> PopMessage<T> for WorkletMessages<T> {
+    fn pop(&self) -> Option<T> {
+        self.messages.borrow_mut().pop_front()
+    }
+}
+
+impl<T: Send> PushMessage<T> for WorkletMessages<T> {
+    fn push(&self, message: T) -> bool {
+        self.messages.borrow_mut().push_back(message);
+        true
+    }
+}
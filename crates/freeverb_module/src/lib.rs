@@ -1,15 +1,22 @@
-#[cfg(target_arch = "wasm32")]
-mod wasm;
-
 use audio_module::{
-    AudioModule, AudioProcessor, BoolParameter, FloatParameter, Parameters, PercentStringConverter,
-    PopMessage, PushMessage, ToProcessor,
+    AudioModule, AudioProcessor, BoolParameter, FloatParameter, Parameter, Parameters,
+    PercentStringConverter, PopMessage, PushMessage, SmoothedValue, Smoothing, ToProcessor,
 };
-use audio_stream::FRAMES_PER_UPDATE;
 use freeverb::{Float, Freeverb};
 
+/// The number of frames included in each [FromFreeverb::ScopeBuffer] message
+pub const SCOPE_BUFFER_FRAMES: usize = 128;
+
+/// The smoothing that's applied to changes to the mix parameters
+const MIX_SMOOTHING: Smoothing = Smoothing::Linear(0.02);
+/// The smoothing that's applied to changes to the reverb's parameters
+const REVERB_SMOOTHING: Smoothing = Smoothing::Exponential(0.05);
+
 pub struct FreeverbModule;
 
+#[cfg(target_arch = "wasm32")]
+audio_stream::export_wasm_processor!(FreeverbModule);
+
 impl AudioModule for FreeverbModule {
     const NAME: &'static str = "Freeverb";
 
@@ -29,7 +36,7 @@ pub enum ToFreeverb {
 #[cfg_attr(target_arch = "wasm32", derive(serde::Serialize, serde::Deserialize))]
 pub enum FromFreeverb {
     #[cfg_attr(target_arch = "wasm32", serde(with = "serde_arrays"))]
-    ScopeBuffer([(f32, f32); FRAMES_PER_UPDATE]),
+    ScopeBuffer([(f32, f32); SCOPE_BUFFER_FRAMES]),
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -85,27 +92,47 @@ impl Default for FreeverbParameters {
                 "Dampening",
                 FreeverbParameterId::Dampening as usize,
             )
+            .with_key("dampening")
+            .with_group("Reverb")
             .string_converter(percent_string_converter())
             .default_user_value(0.75)
+            .smoothing(REVERB_SMOOTHING)
             .build(),
             width: FloatParameter::builder("Width", FreeverbParameterId::Width as usize)
+                .with_key("width")
+                .with_group("Reverb")
                 .string_converter(percent_string_converter())
                 .default_user_value(0.5)
+                .smoothing(MIX_SMOOTHING)
                 .build(),
             room_size: FloatParameter::builder("Room Size", FreeverbParameterId::RoomSize as usize)
+                .with_key("room_size")
+                .with_group("Reverb")
                 .string_converter(percent_string_converter())
                 .default_user_value(0.25)
+                .smoothing(REVERB_SMOOTHING)
                 .build(),
-            freeze: BoolParameter::new("Freeze", FreeverbParameterId::Freeze as usize, false),
+            freeze: BoolParameter::new("Freeze", FreeverbParameterId::Freeze as usize, false)
+                .with_key("freeze")
+                .with_group("Reverb"),
             dry: FloatParameter::builder("Dry", FreeverbParameterId::Dry as usize)
+                .with_key("dry")
+                .with_group("Mix")
                 .string_converter(percent_string_converter())
                 .default_user_value(0.0)
+                .smoothing(MIX_SMOOTHING)
                 .build(),
             wet: FloatParameter::builder("Wet", FreeverbParameterId::Wet as usize)
+                .with_key("wet")
+                .with_group("Mix")
                 .string_converter(percent_string_converter())
                 .default_user_value(0.33)
+                .smoothing(MIX_SMOOTHING)
                 .build(),
-            scope: BoolParameter::new("Scope", FreeverbParameterId::Scope as usize, false),
+            // The scope is enabled by the apps when audio is running
+            scope: BoolParameter::new("Scope", FreeverbParameterId::Scope as usize, false)
+                .with_key("scope")
+                .hidden(),
         }
     }
 }
@@ -114,18 +141,74 @@ fn percent_string_converter() -> PercentStringConverter {
     PercentStringConverter::default()
 }
 
-impl Parameters for FreeverbParameters {}
+impl Parameters for FreeverbParameters {
+    fn parameters(&self) -> Vec<&dyn Parameter> {
+        vec![
+            &self.dampening,
+            &self.width,
+            &self.room_size,
+            &self.freeze,
+            &self.dry,
+            &self.wet,
+            &self.scope,
+        ]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut dyn Parameter> {
+        vec![
+            &mut self.dampening,
+            &mut self.width,
+            &mut self.room_size,
+            &mut self.freeze,
+            &mut self.dry,
+            &mut self.wet,
+            &mut self.scope,
+        ]
+    }
+}
 
 pub struct FreeverbProcessor<T: Float = f64> {
     freeverb: Freeverb<T>,
+    // Parameter changes are smoothed and applied to the reverb a frame at a time
+    dampening: SmoothedValue,
+    width: SmoothedValue,
+    room_size: SmoothedValue,
+    dry: SmoothedValue,
+    wet: SmoothedValue,
     scope_enabled: bool,
+    // Processed frames are collected here until a full scope buffer is available
+    scope_buffer: [(f32, f32); SCOPE_BUFFER_FRAMES],
+    scope_buffer_position: usize,
 }
 
 impl<T: Float> FreeverbProcessor<T> {
     pub fn new(sample_rate: usize) -> Self {
+        let parameters = FreeverbParameters::default();
+        let smoothed = |parameter: &FloatParameter| {
+            SmoothedValue::new(
+                parameter.default_user_value(),
+                parameter.smoothing(),
+                sample_rate,
+            )
+        };
+
+        let mut freeverb = Freeverb::new(sample_rate);
+        freeverb.set_dampening(parameters.dampening.default_user_value().into());
+        freeverb.set_width(parameters.width.default_user_value().into());
+        freeverb.set_room_size(parameters.room_size.default_user_value().into());
+        freeverb.set_dry(parameters.dry.default_user_value().into());
+        freeverb.set_wet(parameters.wet.default_user_value().into());
+
         Self {
-            freeverb: Freeverb::new(sample_rate),
+            freeverb,
+            dampening: smoothed(&parameters.dampening),
+            width: smoothed(&parameters.width),
+            room_size: smoothed(&parameters.room_size),
+            dry: smoothed(&parameters.dry),
+            wet: smoothed(&parameters.wet),
             scope_enabled: false,
+            scope_buffer: [(0.0, 0.0); SCOPE_BUFFER_FRAMES],
+            scope_buffer_position: 0,
         }
     }
 
@@ -138,26 +221,17 @@ impl<T: Float> FreeverbProcessor<T> {
                 };
 
                 match parameter_id {
-                    FreeverbParameterId::Dampening => {
-                        self.freeverb.set_dampening(value.into());
-                    }
-                    FreeverbParameterId::Width => {
-                        self.freeverb.set_width(value.into());
-                    }
-                    FreeverbParameterId::RoomSize => {
-                        self.freeverb.set_room_size(value.into());
-                    }
+                    FreeverbParameterId::Dampening => self.dampening.set_target(value),
+                    FreeverbParameterId::Width => self.width.set_target(value),
+                    FreeverbParameterId::RoomSize => self.room_size.set_target(value),
                     FreeverbParameterId::Freeze => {
                         self.freeverb.set_freeze(value != 0.0);
                     }
-                    FreeverbParameterId::Dry => {
-                        self.freeverb.set_dry(value.into());
-                    }
-                    FreeverbParameterId::Wet => {
-                        self.freeverb.set_wet(value.into());
-                    }
+                    FreeverbParameterId::Dry => self.dry.set_target(value),
+                    FreeverbParameterId::Wet => self.wet.set_target(value),
                     FreeverbParameterId::Scope => {
                         self.scope_enabled = value != 0.0;
+                        self.scope_buffer_position = 0;
                     }
                 }
             }
@@ -165,6 +239,48 @@ impl<T: Float> FreeverbProcessor<T> {
             ToProcessor::EndEdit(_) => {}
         }
     }
+
+    // Advances the smoothed parameters by a frame, applying any that are still changing
+    fn update_smoothed_parameters(&mut self) {
+        if self.dampening.is_smoothing() {
+            self.freeverb.set_dampening(self.dampening.tick().into());
+        }
+        if self.width.is_smoothing() {
+            self.freeverb.set_width(self.width.tick().into());
+        }
+        if self.room_size.is_smoothing() {
+            self.freeverb.set_room_size(self.room_size.tick().into());
+        }
+        if self.dry.is_smoothing() {
+            self.freeverb.set_dry(self.dry.tick().into());
+        }
+        if self.wet.is_smoothing() {
+            self.freeverb.set_wet(self.wet.tick().into());
+        }
+    }
+
+    // Processes a single frame in place, returning the processed left and right values
+    //
+    // Mono frames are processed as stereo and then downmixed, and any channels beyond the first
+    // two are left unprocessed.
+    fn process_frame(&mut self, frame: &mut [f32]) -> (f32, f32) {
+        self.update_smoothed_parameters();
+
+        match frame {
+            [mono] => {
+                let (out_left, out_right) = self.freeverb.tick((T::from(*mono), T::from(*mono)));
+                *mono = (out_left.to_f32() + out_right.to_f32()) * 0.5;
+                (*mono, *mono)
+            }
+            [left, right, ..] => {
+                let (out_left, out_right) = self.freeverb.tick((T::from(*left), T::from(*right)));
+                *left = out_left.to_f32();
+                *right = out_right.to_f32();
+                (*left, *right)
+            }
+            [] => (0.0, 0.0),
+        }
+    }
 }
 
 impl<T: Float> AudioProcessor for FreeverbProcessor<T> {
@@ -180,33 +296,25 @@ impl<T: Float> AudioProcessor for FreeverbProcessor<T> {
         To: PopMessage<ToProcessor>,
         From: PushMessage<Self::OutputMessage>,
     {
-        debug_assert_eq!(channels, 2);
-        let (frames, remainder) = buffer.as_chunks_mut::<2>();
-        debug_assert_eq!(remainder.len(), 0);
+        debug_assert_eq!(buffer.len() % channels, 0);
 
         while let Some(message) = to_processor.pop() {
             self.receive_message(message);
         }
 
         if self.scope_enabled {
-            let mut scope_buffer = [(0.0, 0.0); FRAMES_PER_UPDATE];
-
-            for (process_frame, scope_frame) in frames.iter_mut().zip(scope_buffer.iter_mut()) {
-                let (out_left, out_right) = self
-                    .freeverb
-                    .tick((T::from(process_frame[0]), T::from(process_frame[1])));
-                process_frame[0] = out_left.to_f32();
-                process_frame[1] = out_right.to_f32();
-                *scope_frame = (process_frame[0], process_frame[1]);
-            }
+            for frame in buffer.chunks_exact_mut(channels) {
+                self.scope_buffer[self.scope_buffer_position] = self.process_frame(frame);
+                self.scope_buffer_position += 1;
 
-            from_processor.push(FromFreeverb::ScopeBuffer(scope_buffer));
+                if self.scope_buffer_position == SCOPE_BUFFER_FRAMES {
+                    from_processor.push(FromFreeverb::ScopeBuffer(self.scope_buffer));
+                    self.scope_buffer_position = 0;
+                }
+            }
         } else {
-            for frame in frames.iter_mut() {
-                let (out_left, out_right) =
-                    self.freeverb.tick((T::from(frame[0]), T::from(frame[1])));
-                frame[0] = out_left.to_f32();
-                frame[1] = out_right.to_f32();
+            for frame in buffer.chunks_exact_mut(channels) {
+                self.process_frame(frame);
             }
         }
     }
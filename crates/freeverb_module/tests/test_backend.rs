@@ -0,0 +1,147 @@
+use audio_module::{PopMessage, PushMessage, ToProcessor};
+use audio_stream::{StreamConfig, StreamEvent, TestBackend};
+use freeverb_module::{FreeverbModule, FreeverbParameterId, FromFreeverb, SCOPE_BUFFER_FRAMES};
+
+const SAMPLE_RATE: usize = 44100;
+
+fn backend(frames_per_update: usize) -> TestBackend<FreeverbModule> {
+    TestBackend::new(StreamConfig {
+        frames_per_update,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+// Interleaved stereo input with a non-zero signal in both channels
+fn input(frames: usize) -> Vec<f32> {
+    (0..frames)
+        .flat_map(|frame| {
+            let value = (frame as f32 * 0.05).sin() * 0.5;
+            [value, -value]
+        })
+        .collect()
+}
+
+fn set_parameter(backend: &TestBackend<FreeverbModule>, id: FreeverbParameterId, value: f32) {
+    assert!(
+        backend
+            .to_processor()
+            .push(ToProcessor::SetParameter(id.as_usize(), value))
+    );
+}
+
+fn scope_buffer_count(backend: &TestBackend<FreeverbModule>) -> usize {
+    let from_processor = backend.from_processor();
+    std::iter::from_fn(|| from_processor.pop())
+        .filter(|message| matches!(message, FromFreeverb::ScopeBuffer(_)))
+        .count()
+}
+
+#[test]
+fn set_parameter_changes_output() {
+    let frames = SAMPLE_RATE / 2;
+
+    let mut reference = backend(128);
+    reference.inject_input(&input(frames));
+    reference.advance(frames);
+    let reference_output = reference.take_output();
+
+    // Silence the reverb before any audio is processed
+    let mut muted = backend(128);
+    set_parameter(&muted, FreeverbParameterId::Dry, 0.0);
+    set_parameter(&muted, FreeverbParameterId::Wet, 0.0);
+    muted.inject_input(&input(frames));
+    muted.advance(frames);
+    let muted_output = muted.take_output();
+
+    assert_eq!(muted_output.len(), reference_output.len());
+    assert_ne!(muted_output, reference_output);
+
+    // The wet level is smoothed, so only check the output after the smoothing has finished
+    let settled = &muted_output[muted_output.len() / 2..];
+    assert!(settled.iter().all(|sample| sample.abs() < 1.0e-6));
+    let reference_settled = &reference_output[reference_output.len() / 2..];
+    assert!(reference_settled.iter().any(|sample| sample.abs() > 1.0e-3));
+}
+
+#[test]
+fn scope_buffers_arrive_every_scope_buffer_frames() {
+    // The block size isn't a multiple of the scope buffer size
+    let mut backend = backend(100);
+
+    backend.advance(SCOPE_BUFFER_FRAMES * 2);
+    assert_eq!(scope_buffer_count(&backend), 0);
+
+    set_parameter(&backend, FreeverbParameterId::Scope, 1.0);
+    for _ in 0..4 {
+        backend.advance(SCOPE_BUFFER_FRAMES - 1);
+        assert_eq!(scope_buffer_count(&backend), 0);
+        backend.advance(1);
+        assert_eq!(scope_buffer_count(&backend), 1);
+    }
+
+    backend.advance(SCOPE_BUFFER_FRAMES * 10);
+    assert_eq!(scope_buffer_count(&backend), 10);
+
+    set_parameter(&backend, FreeverbParameterId::Scope, 0.0);
+    backend.advance(SCOPE_BUFFER_FRAMES * 2);
+    assert_eq!(scope_buffer_count(&backend), 0);
+}
+
+#[test]
+fn restart_keeps_the_processor() {
+    let mut backend = backend(64);
+    let events = backend.events();
+
+    set_parameter(&backend, FreeverbParameterId::Scope, 1.0);
+    backend.advance(SCOPE_BUFFER_FRAMES / 2);
+
+    backend.restart().unwrap();
+
+    // The processor's scope position is kept across the restart
+    backend.advance(SCOPE_BUFFER_FRAMES / 2);
+    assert_eq!(scope_buffer_count(&backend), 1);
+    assert_eq!(backend.position(), SCOPE_BUFFER_FRAMES);
+
+    let events: Vec<_> = events.try_iter().collect();
+    assert!(matches!(
+        events.as_slice(),
+        [
+            StreamEvent::Started { .. },
+            StreamEvent::Stopped,
+            StreamEvent::Started { .. },
+            StreamEvent::StreamRestarted,
+        ]
+    ));
+}
+
+#[test]
+fn restart_discards_pending_input() {
+    let mut backend = backend(64);
+
+    backend.inject_input(&input(256));
+    backend.advance(64);
+    assert_eq!(backend.pending_input_frames(), 192);
+
+    backend.restart().unwrap();
+    assert_eq!(backend.pending_input_frames(), 0);
+}
+
+#[test]
+fn recreated_processor_starts_from_defaults() {
+    let mut backend = backend(64);
+
+    set_parameter(&backend, FreeverbParameterId::Scope, 1.0);
+    backend.advance(SCOPE_BUFFER_FRAMES);
+    assert_eq!(scope_buffer_count(&backend), 1);
+
+    backend.recreate_processor().unwrap();
+
+    // The new processor has the scope disabled, but still receives messages
+    backend.advance(SCOPE_BUFFER_FRAMES * 2);
+    assert_eq!(scope_buffer_count(&backend), 0);
+
+    set_parameter(&backend, FreeverbParameterId::Scope, 1.0);
+    backend.advance(SCOPE_BUFFER_FRAMES * 2);
+    assert_eq!(scope_buffer_count(&backend), 2);
+}
@@ -1,6 +1,9 @@
 use std::sync::Arc;
 
-use audio_module::{BoolParameter, FloatParameter, Parameter, PushMessage, ToProcessor};
+use audio_module::{
+    BoolParameter, EnumParameter, FloatParameter, IntParameter, Parameter, Parameters, PushMessage,
+    ToProcessor,
+};
 use audio_stream::ToProcessorSender;
 use freeverb_module::{FreeverbParameterId, FreeverbParameters};
 use nih_plug::{
@@ -33,7 +36,7 @@ impl<E: FreeverbEditor> FreeverbParams<E> {
     pub fn new(to_processor: ToProcessorSender) -> Self {
         let params = FreeverbParameters::default();
 
-        Self {
+        let plugin_params = Self {
             dampening: percent_parameter(params.dampening, to_processor.clone()),
             width: percent_parameter(params.width, to_processor.clone()),
             room_size: percent_parameter(params.room_size, to_processor.clone()),
@@ -42,7 +45,19 @@ impl<E: FreeverbEditor> FreeverbParams<E> {
             wet: percent_parameter(params.wet, to_processor.clone()),
 
             editor_state: E::make_editor_state(),
-        }
+        };
+
+        // The `#[id]`s are used by hosts for automation and saved state, so they need to match the
+        // module's stable parameter keys.
+        debug_assert!(
+            plugin_params
+                .param_map()
+                .iter()
+                .all(|(id, _, _)| FreeverbParameters::default().parameter_by_key(id).is_some()),
+            "The plugin's parameter ids don't match the module's parameter keys"
+        );
+
+        plugin_params
     }
 }
 
@@ -93,6 +108,12 @@ impl PlainFromF32 for bool {
     }
 }
 
+impl PlainFromF32 for i32 {
+    fn from_f32(value: f32) -> Self {
+        value.round() as i32
+    }
+}
+
 fn percent_parameter(param: FloatParameter, to_processor: ToProcessorSender) -> FloatParam {
     let id = param.id();
 
@@ -120,3 +141,126 @@ fn bool_parameter(param: BoolParameter, to_processor: ToProcessorSender) -> Bool
         }),
     )
 }
+
+/// Maps an [IntParameter] to an `IntParam`, with values snapped to the parameter's step
+pub fn int_parameter(param: IntParameter, to_processor: ToProcessorSender) -> IntParam {
+    let string_converter = param.string_converter();
+
+    IntParam::new(
+        param.name().to_string(),
+        param.default_user_value() as i32,
+        IntRange::Linear {
+            min: param.min(),
+            max: param.max(),
+        },
+    )
+    .with_value_to_string(Arc::new(move |value| {
+        string_converter.to_string_with_unit(value as f32)
+    }))
+    .with_callback(int_parameter_callback(param, to_processor))
+}
+
+/// Sends an [IntParameter]'s new values to the processor, snapped to the parameter's step
+fn int_parameter_callback(
+    param: IntParameter,
+    to_processor: ToProcessorSender,
+) -> Arc<dyn Fn(i32) + Send + Sync> {
+    let id = param.id();
+
+    Arc::new(move |value| {
+        let mut snapped = param.clone();
+        snapped.set_user_value(value as f32);
+        to_processor.push(ToProcessor::SetParameter(id, snapped.user_value()));
+    })
+}
+
+/// Maps an [EnumParameter] to an `IntParam` that's displayed with the variants' labels
+///
+/// `nih_plug`'s `EnumParam` needs the variants to be known at compile time, so the variant's
+/// index is used as the param's value instead.
+pub fn enum_parameter(param: EnumParameter, to_processor: ToProcessorSender) -> IntParam {
+    let id = param.id();
+    let to_string_converter = param.string_converter();
+    let from_string_converter = param.string_converter();
+
+    IntParam::new(
+        param.name().to_string(),
+        param.default_user_value() as i32,
+        IntRange::Linear {
+            min: 0,
+            max: param.labels().len().saturating_sub(1) as i32,
+        },
+    )
+    .with_value_to_string(Arc::new(move |value| {
+        to_string_converter.to_string(value as f32)
+    }))
+    .with_string_to_value(Arc::new(move |label| {
+        from_string_converter
+            .to_f32(label)
+            .map(|index| index as i32)
+    }))
+    .with_callback(Arc::new(move |value| {
+        to_processor.push(ToProcessor::SetParameter(id, value as f32));
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audio_module::PopMessage;
+    use audio_stream::ToProcessorReceiver;
+    use crossbeam_channel::unbounded;
+
+    fn channel() -> (ToProcessorSender, ToProcessorReceiver) {
+        let (sender, receiver) = unbounded();
+        (
+            ToProcessorSender::new(sender),
+            ToProcessorReceiver::new(receiver),
+        )
+    }
+
+    #[test]
+    fn int_params_use_the_parameters_range_and_step() {
+        let parameter = IntParameter::builder("Transpose", 3, -12..=12)
+            .default_user_value(0)
+            .step(3)
+            .build();
+        let (to_processor, from_plugin) = channel();
+
+        let param = int_parameter(parameter.clone(), to_processor.clone());
+        assert_eq!(param.name(), "Transpose");
+        assert_eq!(param.default_plain_value(), 0);
+        assert_eq!(param.preview_plain(0.0), -12);
+        assert_eq!(param.preview_plain(1.0), 12);
+
+        // Values from the host are snapped to the parameter's step before they're sent
+        let callback = int_parameter_callback(parameter, to_processor);
+        callback(4);
+        callback(-8);
+        assert!(matches!(
+            from_plugin.pop(),
+            Some(ToProcessor::SetParameter(3, 3.0))
+        ));
+        assert!(matches!(
+            from_plugin.pop(),
+            Some(ToProcessor::SetParameter(3, -9.0))
+        ));
+        assert!(from_plugin.pop().is_none());
+    }
+
+    #[test]
+    fn enum_params_are_displayed_with_the_variants_labels() {
+        let parameter = EnumParameter::new("Mode", 4, &["Low", "Band", "High"], 1);
+        let (to_processor, _from_plugin) = channel();
+
+        let param = enum_parameter(parameter, to_processor);
+        assert_eq!(param.step_count(), Some(2));
+        assert_eq!(param.default_plain_value(), 1);
+        assert_eq!(param.preview_plain(1.0), 2);
+
+        assert_eq!(param.normalized_value_to_string(0.0, false), "Low");
+        assert_eq!(param.normalized_value_to_string(0.5, false), "Band");
+        assert_eq!(param.string_to_normalized_value("High"), Some(1.0));
+        assert_eq!(param.string_to_normalized_value("Off"), None);
+    }
+}